@@ -0,0 +1,281 @@
+use bevy::{
+    camera::visibility::RenderLayers, gltf::Gltf, prelude::*, scene::SceneInstanceReady,
+    transform::TransformSystems,
+};
+use bevy_rapier3d::prelude::*;
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraController, LookTransform};
+
+use crate::form::Form;
+use crate::input::{Action, ActionState};
+use crate::scenes::SceneHandle;
+use crate::setup::{AppState, InGame, PauseState};
+
+// The camera either orbits the pass (the original view, mouse / right stick / two fingers),
+// follows the Form from behind, turning with it, or looks out through the Form's glasses. The
+// camera action cycles through them. The Form's meshes live on their own render layer so the
+// first-person camera can leave them out.
+//
+// The Form moves on physics ticks, which don't line up with frames. The cameras follow its pose
+// blended between the last two ticks instead, so they glide even on displays faster than the
+// tick rate.
+
+/// Render layer of the player's Form, seen by every camera mode except first person.
+pub const FORM_LAYER: usize = 1;
+
+/// How far behind and above the Form the follow camera sits.
+const FOLLOW_DISTANCE: f32 = 14.0;
+const FOLLOW_HEIGHT: f32 = 5.0;
+/// Looks a little above the Form's origin.
+const FOLLOW_TARGET_HEIGHT: f32 = 1.5;
+/// Spring stiffness, the spring is critically damped so it settles without overshooting.
+const FOLLOW_STIFFNESS: f32 = 30.0;
+/// Space kept between the camera and terrain it would otherwise clip into.
+const CLEARANCE: f32 = 0.5;
+/// Where the glasses sit on the `FORM` mesh, in front of and above its origin.
+const GLASSES_OFFSET: Vec3 = Vec3::new(1.1, 1.0, 0.0);
+/// The `glasses_glass` material's color, for when the glTF doesn't have it.
+const GLASS_TINT: Color = Color::srgb(0.16, 1.0, 0.0);
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraMode>()
+            .init_resource::<FormPose>()
+            .add_systems(OnEnter(AppState::Running), (
+                spawn_glass_overlay,
+            ))
+            .add_systems(FixedUpdate, (
+                record_form_pose.after(PhysicsSet::Writeback),
+            ))
+            .add_systems(Update, (
+                switch_camera_mode.run_if(in_state(PauseState::Playing)),
+                apply_camera_mode.after(switch_camera_mode),
+                follow_camera
+                    .after(apply_camera_mode)
+                    .run_if(in_state(AppState::Running).and(not(in_state(PauseState::Photo)))),
+            ))
+            // after the look transform has been written, so nothing smooths the view behind the glasses
+            .add_systems(PostUpdate, (
+                first_person_camera
+                    .before(TransformSystems::Propagate)
+                    .run_if(in_state(AppState::Running).and(not(in_state(PauseState::Photo)))),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_form_pose,
+            ));
+    }
+}
+
+/// The Form's pose on the last two physics ticks.
+#[derive(Resource, Default, Debug)]
+pub struct FormPose {
+    previous: Transform,
+    current: Option<Transform>,
+}
+
+impl FormPose {
+    /// Where the Form is `fraction` of the way from the previous tick to the latest one, `None`
+    /// before its first tick.
+    pub fn blended(&self, fraction: f32) -> Option<Transform> {
+        let current = self.current?;
+        Some(Transform {
+            translation: self.previous.translation.lerp(current.translation, fraction),
+            rotation: self.previous.rotation.slerp(current.rotation, fraction),
+            scale: current.scale,
+        })
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Orbit,
+    Follow,
+    FirstPerson,
+}
+
+impl CameraMode {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Orbit => Self::Follow,
+            Self::Follow => Self::FirstPerson,
+            Self::FirstPerson => Self::Orbit,
+        }
+    }
+}
+
+pub fn record_form_pose(form_query: Query<&Transform, With<Form>>, mut pose: ResMut<FormPose>) {
+    let Ok(form) = form_query.single() else {
+        return;
+    };
+    pose.previous = pose.current.unwrap_or(*form);
+    pose.current = Some(*form);
+}
+
+pub fn reset_form_pose(mut pose: ResMut<FormPose>) {
+    *pose = FormPose::default();
+}
+
+pub fn switch_camera_mode(actions: Res<ActionState>, mut mode: ResMut<CameraMode>) {
+    if actions.just_pressed(Action::Camera) {
+        *mode = mode.next();
+        bevy::log::info!("Camera mode: {:?}", *mode);
+    }
+}
+
+/// The see-through tint and vignette of the glasses, shown in first person.
+#[derive(Component)]
+pub struct GlassOverlay;
+
+/// Puts the Form's meshes on `FORM_LAYER` once its scene has spawned. Lights attached to the
+/// Form stay on the default layer, they light the pass in every camera mode.
+pub fn put_on_form_layer(
+    ready: On<SceneInstanceReady>,
+    children: Query<&Children>,
+    meshes: Query<(), With<Mesh3d>>,
+    mut commands: Commands,
+) {
+    for entity in children.iter_descendants(ready.entity) {
+        if meshes.contains(entity) {
+            commands.entity(entity).insert(RenderLayers::layer(FORM_LAYER));
+        }
+    }
+}
+
+pub fn spawn_glass_overlay(
+    mut commands: Commands,
+    gltf_assets: Res<Assets<Gltf>>,
+    materials: Res<Assets<StandardMaterial>>,
+    scene_handle: Res<SceneHandle>,
+) {
+    let tint = gltf_assets
+        .get(&scene_handle.handle)
+        .and_then(|scenes_gltf| scenes_gltf.named_materials.get("glasses_glass"))
+        .and_then(|material_handle| materials.get(material_handle))
+        .map(|material| material.base_color)
+        .unwrap_or(GLASS_TINT);
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundGradient::from(RadialGradient::new(
+            UiPosition::CENTER,
+            RadialGradientShape::FarthestCorner,
+            vec![
+                ColorStop::percent(tint.with_alpha(0.04), 0.),
+                ColorStop::percent(tint.with_alpha(0.06), 55.),
+                ColorStop::percent(Color::BLACK.with_alpha(0.6), 100.),
+            ],
+        )),
+        Visibility::Hidden,
+        GlassOverlay,
+        InGame,
+    ));
+}
+
+/// Hands the camera to the orbit controller only in orbit mode, and hides the Form and shows
+/// the glasses in first person.
+pub fn apply_camera_mode(
+    mode: Res<CameraMode>,
+    new_overlays: Query<(), Added<GlassOverlay>>,
+    mut commands: Commands,
+    mut controllers: Query<(Entity, &mut OrbitCameraController)>,
+    mut overlays: Query<&mut Visibility, With<GlassOverlay>>,
+) {
+    if !mode.is_changed() && new_overlays.is_empty() {
+        return;
+    }
+    let first_person = *mode == CameraMode::FirstPerson;
+
+    for (entity, mut controller) in controllers.iter_mut() {
+        controller.enabled = *mode == CameraMode::Orbit;
+        let layers = if first_person {
+            RenderLayers::layer(0)
+        } else {
+            RenderLayers::layer(0).with(FORM_LAYER)
+        };
+        commands.entity(entity).insert(layers);
+    }
+    for mut visibility in overlays.iter_mut() {
+        *visibility = if first_person { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn follow_camera(
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+    mode: Res<CameraMode>,
+    rapier_context: ReadRapierContext,
+    form_pose: Res<FormPose>,
+    mut cameras: Query<&mut LookTransform>,
+    mut velocity: Local<Vec3>,
+) {
+    if *mode != CameraMode::Follow {
+        *velocity = Vec3::ZERO;
+        return;
+    }
+    let Some(form) = form_pose.blended(fixed_time.overstep_fraction()) else {
+        return;
+    };
+
+    // the Form faces +x, so behind it is -x in its frame
+    let target = form.translation + Vec3::Y * FOLLOW_TARGET_HEIGHT;
+    let desired = target + form.rotation * Vec3::NEG_X * FOLLOW_DISTANCE + Vec3::Y * FOLLOW_HEIGHT;
+    let dt = time.delta_secs().min(0.1);
+
+    for mut look in cameras.iter_mut() {
+        let acceleration = (desired - look.eye) * FOLLOW_STIFFNESS - *velocity * 2.0 * FOLLOW_STIFFNESS.sqrt();
+        *velocity += acceleration * dt;
+        let mut eye = look.eye + *velocity * dt;
+
+        // pull in front of any terrain between the Form and the camera
+        if let Ok(context) = rapier_context.single() {
+            let offset = eye - target;
+            let distance = offset.length();
+            if distance > CLEARANCE
+                && let Some((_, hit)) = context.cast_ray(
+                    target,
+                    offset / distance,
+                    distance,
+                    true,
+                    QueryFilter::only_fixed().exclude_sensors(),
+                )
+            {
+                eye = target + offset / distance * (hit - CLEARANCE).max(CLEARANCE);
+            }
+        }
+
+        look.eye = eye;
+        look.target = target;
+    }
+}
+
+/// Mounts the camera at the glasses, looking where the Form faces.
+pub fn first_person_camera(
+    fixed_time: Res<Time<Fixed>>,
+    mode: Res<CameraMode>,
+    form_pose: Res<FormPose>,
+    mut cameras: Query<(&mut Transform, &mut LookTransform)>,
+) {
+    if *mode != CameraMode::FirstPerson {
+        return;
+    }
+    let Some(form) = form_pose.blended(fixed_time.overstep_fraction()) else {
+        return;
+    };
+
+    let eye = form.transform_point(GLASSES_OFFSET);
+    let forward = form.rotation * Vec3::X;
+    for (mut transform, mut look) in cameras.iter_mut() {
+        *transform = Transform::from_translation(eye).looking_to(forward, Vec3::Y);
+        // switching back blends out from the glasses
+        look.eye = eye;
+        look.target = eye + forward;
+    }
+}
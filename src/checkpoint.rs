@@ -0,0 +1,204 @@
+use bevy::{
+    gltf::{Gltf, GltfNode},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::form::{self, Form};
+use crate::gltf_collider;
+use crate::level_nodes::{self, LevelLayout};
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, InGame, PauseState};
+
+// The Form respawns at the last checkpoint it passed whenever it leaves the level's bounds or
+// drops below a kill plane, behind a short fade to black. Checkpoints come from
+// `CheckpointPlacements`, the level's `checkpoints` in the manifest, glTF nodes named
+// `CHECKPOINT...` and empties marked as checkpoints (see `level_nodes`), the level's spawn point
+// always counts as the first one.
+
+/// How close the Form has to pass by a checkpoint to reach it.
+const REACH_RADIUS: f32 = 6.0;
+/// Length of the fade out, and again of the fade back in.
+const FADE_SECS: f32 = 0.3;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CheckpointPlacements>()
+            .init_resource::<RespawnPoint>()
+            .init_resource::<Respawn>()
+            .add_message::<CheckpointReached>()
+            .add_systems(OnEnter(AppState::Running), (
+                spawn_checkpoints.after(level_nodes::read_level_layout),
+                spawn_fade,
+            ))
+            // on physics ticks in a set order, so respawns land on the same tick in a replay
+            .add_systems(FixedUpdate, (
+                reach_checkpoints
+                    .before(respawn)
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(PauseState::Playing)),
+                leave_bounds.before(PhysicsSet::SyncBackend).run_if(in_state(PauseState::Playing)),
+                respawn
+                    .after(leave_bounds)
+                    .after(form::apply_movement)
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_respawn,
+            ));
+    }
+}
+
+/// Checkpoints on every level, before the level's own. Insert before adding the plugin to add
+/// some.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CheckpointPlacements(pub Vec<Transform>);
+
+/// Numbered in course order, placements first, then the manifest's, then glTF nodes by name,
+/// then marked empties.
+#[derive(Component)]
+pub struct Checkpoint(pub usize);
+
+/// Sent when the Form reaches a checkpoint it didn't respawn at last.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct CheckpointReached(pub usize);
+
+/// The last checkpoint reached this run, position and orientation.
+#[derive(Resource, Default, Debug)]
+pub struct RespawnPoint(pub Transform);
+
+/// A respawn in progress, `elapsed` runs from the start of the fade out to the end of the fade in.
+#[derive(Resource, Default, Debug)]
+pub struct Respawn {
+    elapsed: Option<f32>,
+    moved: bool,
+}
+
+impl Respawn {
+    pub fn start(&mut self) {
+        if self.elapsed.is_none() {
+            self.elapsed = Some(0.0);
+            self.moved = false;
+        }
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.elapsed.is_some()
+    }
+}
+
+/// Full screen overlay faded in and out around a respawn.
+#[derive(Component)]
+pub struct Fade;
+
+pub fn spawn_checkpoints(
+    mut commands: Commands,
+    gltf_assets: Res<Assets<Gltf>>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    current_level: Res<CurrentLevel>,
+    placements: Res<CheckpointPlacements>,
+    layout: Res<LevelLayout>,
+    mut respawn_point: ResMut<RespawnPoint>,
+) {
+    respawn_point.0 = layout.spawn;
+
+    let mut transforms = placements.0.clone();
+    transforms.extend(current_level.level.checkpoints.iter().copied().map(Transform::from_translation));
+    if let Some(scenes_gltf) = gltf_assets.get(&current_level.gltf) {
+        transforms.extend(gltf_collider::named_node_transforms(scenes_gltf, &gltf_nodes, "CHECKPOINT"));
+    }
+    transforms.extend(layout.checkpoints.iter().copied());
+
+    bevy::log::info!("Spawning {} checkpoints", transforms.len());
+    for (index, transform) in transforms.into_iter().enumerate() {
+        commands.spawn((transform, Checkpoint(index), InGame));
+    }
+}
+
+pub fn spawn_fade(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.0)),
+        Fade,
+        InGame,
+    ));
+}
+
+pub fn reach_checkpoints(
+    form_query: Query<&Transform, With<Form>>,
+    checkpoints: Query<(&Transform, &Checkpoint)>,
+    respawn: Res<Respawn>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    mut reached: MessageWriter<CheckpointReached>,
+) {
+    let Ok(form_transform) = form_query.single() else {
+        return;
+    };
+    if respawn.in_progress() {
+        return;
+    }
+    for (transform, checkpoint) in checkpoints.iter() {
+        if transform.translation != respawn_point.0.translation
+            && transform.translation.distance(form_transform.translation) < REACH_RADIUS
+        {
+            respawn_point.0 = *transform;
+            reached.write(CheckpointReached(checkpoint.0));
+            bevy::log::info!("Checkpoint {} reached at {}", checkpoint.0, transform.translation);
+        }
+    }
+}
+
+pub fn leave_bounds(form_query: Query<&Transform, With<Form>>, layout: Res<LevelLayout>, mut respawn: ResMut<Respawn>) {
+    if let Ok(transform) = form_query.single() {
+        if !layout.contains(transform.translation) {
+            respawn.start();
+        }
+    }
+}
+
+/// Fades out, puts the Form back at the respawn point at a standstill, then fades back in.
+pub fn respawn(
+    time: Res<Time>,
+    respawn_point: Res<RespawnPoint>,
+    mut respawn: ResMut<Respawn>,
+    mut form_query: Query<(&mut Transform, &mut Velocity, &mut ExternalForce), With<Form>>,
+    mut fade_query: Query<&mut BackgroundColor, With<Fade>>,
+) {
+    let Some(elapsed) = respawn.elapsed else {
+        return;
+    };
+    let elapsed = elapsed + time.delta_secs();
+
+    if elapsed >= FADE_SECS && !respawn.moved {
+        for (mut transform, mut velocity, mut force) in form_query.iter_mut() {
+            *transform = respawn_point.0;
+            *velocity = Velocity::zero();
+            *force = ExternalForce::default();
+        }
+        respawn.moved = true;
+    }
+
+    let alpha = if elapsed < FADE_SECS {
+        elapsed / FADE_SECS
+    } else {
+        1.0 - (elapsed - FADE_SECS) / FADE_SECS
+    };
+    for mut background in fade_query.iter_mut() {
+        background.0 = Color::BLACK.with_alpha(alpha.clamp(0.0, 1.0));
+    }
+
+    respawn.elapsed = if elapsed < FADE_SECS * 2.0 { Some(elapsed) } else { None };
+}
+
+pub fn reset_respawn(mut respawn: ResMut<Respawn>) {
+    *respawn = Respawn::default();
+}
@@ -0,0 +1,606 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    gltf::Gltf,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::checkpoint::{self, Checkpoint, CheckpointPlacements, RespawnPoint};
+use crate::form::Form;
+use crate::level_nodes::{self, LevelLayout};
+use crate::levels::{CurrentLevel, Level};
+use crate::lights;
+use crate::scenes::SceneHandle;
+use crate::setup::{spawn_text_button, AppState, ButtonActionQuery, InGame, PauseState};
+use crate::touch::ClearOfJoystick;
+
+// The endless pass: terrain generated from a seed instead of loaded from a glTF, a valley
+// winding along +x through noisy mountains that never ends. It's built in chunks across the
+// whole width of the pass, each with its mesh, a heightfield collider, a light and a checkpoint,
+// spawned ahead of the Form and dropped behind it, all but the one under the last checkpoint
+// reached. The same seed always makes the same pass, it's shown on the level select screen and
+// during the run so a good one can be shared, and a recording made on it replays with it (see
+// `replay`).
+
+/// Where the Form starts.
+const START_X: f32 = -45.0;
+/// How high above the valley floor the Form starts.
+const SPAWN_HEIGHT: f32 = 3.0;
+/// The start line, just ahead of the Form.
+const START_LINE_X: f32 = -40.0;
+/// Where the race finishes, a kilometre in.
+const FINISH_X: f32 = 1000.0;
+/// How far below the valley floor the start and finish volumes reach down.
+const LINE_DEPTH: f32 = 5.0;
+/// Limbo gates stand in the valley every `GATE_SPACING` from `FIRST_GATE_X` up to the finish.
+const FIRST_GATE_X: f32 = 100.0;
+const GATE_SPACING: f32 = 150.0;
+/// The pass spans -`HALF_WIDTH` to `HALF_WIDTH` on z, past that the Form respawns.
+const HALF_WIDTH: f32 = 50.0;
+const CHUNK_LENGTH: f32 = 64.0;
+/// Distance between height samples, in the mesh and the collider.
+const CELL_SIZE: f32 = 2.0;
+const CHUNKS_BEHIND: i32 = 1;
+const CHUNKS_AHEAD: i32 = 3;
+/// The chunk under the start, nothing behind it is in bounds.
+const FIRST_CHUNK: i32 = -1;
+/// Half the height of the bounds, which only limit z and how far back the Form can go.
+const BOUNDS_HEIGHT: f32 = 1000.0;
+/// Long enough to never reach the end of.
+const BOUNDS_LENGTH: f32 = 1_000_000.0;
+const LIGHT_HEIGHT: f32 = 15.0;
+/// Far enough to light the chunks either side, there are as many lights as chunks streamed in.
+const LIGHT_RANGE: f32 = 2.0 * CHUNK_LENGTH;
+const CHECKPOINT_HEIGHT: f32 = 4.0;
+
+/// Half the width of the flat valley floor, and of the slopes up to the mountains.
+const VALLEY_HALF_WIDTH: f32 = 10.0;
+const SLOPE_WIDTH: f32 = 14.0;
+/// The valley runs straight along z = 0 up to here, so a run starts on a straight.
+const STRAIGHT_UNTIL: f32 = 40.0;
+/// How far along x the valley takes to wind in from straight.
+const WIND_IN_LENGTH: f32 = 80.0;
+const WIND_AMPLITUDE: f32 = 40.0;
+const WIND_FREQUENCY: f32 = 0.008;
+const MOUNTAIN_HEIGHT: f32 = 35.0;
+const MOUNTAIN_FREQUENCY: f32 = 0.03;
+const FLOOR_VARIATION: f32 = 1.5;
+const FLOOR_FREQUENCY: f32 = 0.05;
+/// Metres per repeat of the texture coordinates.
+const UV_SCALE: f32 = 10.0;
+
+/// Keep each kind of noise apart, so the winding doesn't follow the peaks.
+const WIND_SALT: u64 = 1;
+const MOUNTAIN_SALT: u64 = 2;
+const FLOOR_SALT: u64 = 3;
+
+/// Keeps seeds short enough to read out and type in.
+const MAX_SEED: u64 = 999_999_999;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EndlessSeed>()
+            .init_resource::<EndlessTerrain>()
+            .add_systems(Update, (
+                handle_seed_buttons.run_if(in_state(AppState::LevelSelect)),
+                type_seed.run_if(in_state(AppState::LevelSelect)),
+                refresh_seed_text.run_if(in_state(AppState::LevelSelect)),
+            ))
+            .add_systems(OnEnter(AppState::Running), (
+                prepare_terrain,
+                extend_bounds.after(level_nodes::read_level_layout),
+                spawn_seed_hud,
+            ))
+            // on physics ticks, so a replay has the same ground under it as the run it recorded
+            .add_systems(FixedUpdate, (
+                stream_chunks
+                    .after(checkpoint::respawn)
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_terrain,
+            ));
+    }
+}
+
+/// The seed the endless pass is made from, set on the level select screen.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct EndlessSeed(pub u64);
+
+impl Default for EndlessSeed {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// The endless pass as a level, with a course of its own laid along the valley. Its
+/// checkpoints come with the chunks.
+pub fn level(seed: u64) -> Level {
+    let generator = PassGenerator::new(seed);
+    let floor = |x: f32| {
+        let z = generator.center(x);
+        Vec3::new(x, generator.height(x, z), z)
+    };
+    // the lines span the whole width of the pass and reach up from a little below the floor
+    let line = |x: f32| Vec3::new(x, floor(x).y - LINE_DEPTH, 0.0);
+    let gates = (0..)
+        .map(|gate| FIRST_GATE_X + gate as f32 * GATE_SPACING)
+        .take_while(|x| *x < FINISH_X)
+        .map(floor)
+        .collect();
+
+    Level {
+        id: format!("endless_{seed}"),
+        name: format!("endless pass {seed}"),
+        gltf: String::new(),
+        terrain_scene: String::new(),
+        terrain_mesh: String::new(),
+        spawn: floor(START_X) + Vec3::Y * SPAWN_HEIGHT,
+        bounds: HALF_WIDTH,
+        start: Some(line(START_LINE_X)),
+        finish: Some(line(FINISH_X)),
+        gates,
+        seed: Some(seed),
+        ..default()
+    }
+}
+
+/// The seed of an endless pass level id, like the one a recording was made on.
+pub fn seed_of(level_id: &str) -> Option<u64> {
+    level_id.strip_prefix("endless_")?.parse().ok()
+}
+
+/// Heights of the endless pass, the same for the same seed wherever they're asked for, so
+/// chunks line up.
+#[derive(Debug, Clone, Copy)]
+pub struct PassGenerator {
+    pub seed: u64,
+}
+
+impl PassGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Where the middle of the valley is on z at `x`.
+    pub fn center(&self, x: f32) -> f32 {
+        let wind_in = smoothstep(STRAIGHT_UNTIL, STRAIGHT_UNTIL + WIND_IN_LENGTH, x);
+        let wind = self.fbm(Vec2::new(x * WIND_FREQUENCY, 0.5), 3, WIND_SALT) * 2.0 - 1.0;
+        // the slopes stay inside the bounds
+        let max_offset = HALF_WIDTH - VALLEY_HALF_WIDTH - SLOPE_WIDTH;
+        (wind * WIND_AMPLITUDE * wind_in).clamp(-max_offset, max_offset)
+    }
+
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        let position = Vec2::new(x, z);
+        let from_center = (z - self.center(x)).abs();
+        let walls = smoothstep(VALLEY_HALF_WIDTH, VALLEY_HALF_WIDTH + SLOPE_WIDTH, from_center);
+        let floor = (self.fbm(position * FLOOR_FREQUENCY, 2, FLOOR_SALT) - 0.5) * FLOOR_VARIATION;
+        let peaks = self.fbm(position * MOUNTAIN_FREQUENCY, 5, MOUNTAIN_SALT);
+        floor + walls * MOUNTAIN_HEIGHT * (0.4 + 0.6 * peaks)
+    }
+
+    /// The mesh and collider of chunk `index`, which spans `CHUNK_LENGTH` along x from
+    /// `index * CHUNK_LENGTH` and the whole width of the pass. Both are centered on the chunk.
+    pub fn chunk(&self, index: i32) -> (Mesh, Collider) {
+        // rows run along z and columns along x, like the heightfield wants them
+        let rows = (2.0 * HALF_WIDTH / CELL_SIZE) as usize + 1;
+        let columns = (CHUNK_LENGTH / CELL_SIZE) as usize + 1;
+        let start_x = index as f32 * CHUNK_LENGTH;
+        let center_x = start_x + CHUNK_LENGTH / 2.0;
+
+        let mut heights = vec![0.0; rows * columns];
+        let mut positions = Vec::with_capacity(rows * columns);
+        let mut uvs = Vec::with_capacity(rows * columns);
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = start_x + column as f32 * CELL_SIZE;
+                let z = -HALF_WIDTH + row as f32 * CELL_SIZE;
+                let height = self.height(x, z);
+                heights[column * rows + row] = height;
+                positions.push([x - center_x, height, z]);
+                uvs.push([x / UV_SCALE, z / UV_SCALE]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity((rows - 1) * (columns - 1) * 6);
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let corner = (row * columns + column) as u32;
+                let (next_x, next_z) = (corner + 1, corner + columns as u32);
+                // counter-clockwise seen from above
+                indices.extend([corner, next_z, next_z + 1, corner, next_z + 1, next_x]);
+            }
+        }
+
+        let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+            .with_computed_smooth_normals();
+        let collider = Collider::heightfield(heights, rows, columns, Vec3::new(CHUNK_LENGTH, 1.0, 2.0 * HALF_WIDTH));
+        (mesh, collider)
+    }
+
+    /// Layered value noise from 0.0 to 1.0, each octave twice as fine and half as strong.
+    fn fbm(&self, position: Vec2, octaves: u32, salt: u64) -> f32 {
+        let (mut total, mut amplitude, mut weight) = (0.0, 1.0, 0.0);
+        for octave in 0..octaves {
+            let octave_seed = self.seed ^ (salt << 32) ^ ((octave as u64) << 48);
+            total += value_noise(octave_seed, position * (1 << octave) as f32) * amplitude;
+            weight += amplitude;
+            amplitude *= 0.5;
+        }
+        total / weight
+    }
+}
+
+/// Smoothly blended random values on a grid, from 0.0 to 1.0.
+fn value_noise(seed: u64, position: Vec2) -> f32 {
+    let cell = position.floor();
+    let t = position - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let (x, z) = (cell.x as i64, cell.y as i64);
+    let corner = |dx: i64, dz: i64| hash(seed, x + dx, z + dz);
+    let near = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * t.x;
+    let far = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * t.x;
+    near + (far - near) * t.y
+}
+
+/// A random value from 0.0 to 1.0 for a grid point, the same every time.
+fn hash(seed: u64, x: i64, z: i64) -> f32 {
+    let h = mix(seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Scrambles every bit of `h` into every other, the splitmix64 finalizer.
+fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The generator and material while the endless pass runs, `None` on other levels.
+#[derive(Resource, Default)]
+pub struct EndlessTerrain {
+    generator: Option<PassGenerator>,
+    material: Handle<StandardMaterial>,
+    /// The number of the first chunk's checkpoint, after the ones every level gets.
+    first_checkpoint: usize,
+}
+
+/// Part of the endless pass, dropped along with the chunk at `.0`.
+#[derive(Component)]
+pub struct EndlessChunk(pub i32);
+
+#[derive(Component)]
+pub struct SeedText;
+
+#[derive(Component, Clone, Copy)]
+pub enum SeedButton {
+    Previous,
+    Next,
+    Random,
+}
+
+/// The seed's row on the level select screen, type digits to enter one.
+pub fn spawn_seed_row(parent: &mut ChildSpawnerCommands, font_handle: Handle<Font>) {
+    parent
+        .spawn(Node {
+            column_gap: Val::Px(12.),
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                Text("seed".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+            ));
+            spawn_text_button(row, "<", font_handle.clone(), 16., SeedButton::Previous);
+            row.spawn((
+                Text::default(),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                TextLayout::new_with_justify(Justify::Center),
+                Node {
+                    width: Val::Px(110.),
+                    ..default()
+                },
+                SeedText,
+            ));
+            spawn_text_button(row, ">", font_handle.clone(), 16., SeedButton::Next);
+            spawn_text_button(row, "random", font_handle.clone(), 16., SeedButton::Random);
+        });
+}
+
+pub fn handle_seed_buttons(
+    mut interaction_query: ButtonActionQuery<SeedButton>,
+    time: Res<Time<Real>>,
+    mut seed: ResMut<EndlessSeed>,
+) {
+    for (interaction, seed_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            seed.0 = match seed_button {
+                SeedButton::Previous => seed.0.saturating_sub(1),
+                SeedButton::Next => (seed.0 + 1).min(MAX_SEED),
+                // when the button was pressed is as random as it needs to be
+                SeedButton::Random => {
+                    let nanos = time.elapsed().as_nanos() as u64;
+                    mix(seed.0 ^ nanos) % (MAX_SEED + 1)
+                }
+            };
+        }
+    }
+}
+
+/// Digits typed on the level select screen go on the end of the seed, backspace takes one off.
+pub fn type_seed(keys: Res<ButtonInput<KeyCode>>, mut seed: ResMut<EndlessSeed>) {
+    const DIGITS: [(KeyCode, KeyCode); 10] = [
+        (KeyCode::Digit0, KeyCode::Numpad0),
+        (KeyCode::Digit1, KeyCode::Numpad1),
+        (KeyCode::Digit2, KeyCode::Numpad2),
+        (KeyCode::Digit3, KeyCode::Numpad3),
+        (KeyCode::Digit4, KeyCode::Numpad4),
+        (KeyCode::Digit5, KeyCode::Numpad5),
+        (KeyCode::Digit6, KeyCode::Numpad6),
+        (KeyCode::Digit7, KeyCode::Numpad7),
+        (KeyCode::Digit8, KeyCode::Numpad8),
+        (KeyCode::Digit9, KeyCode::Numpad9),
+    ];
+    for (digit, (key, numpad_key)) in DIGITS.into_iter().enumerate() {
+        if keys.just_pressed(key) || keys.just_pressed(numpad_key) {
+            let typed = seed.0 * 10 + digit as u64;
+            if typed <= MAX_SEED {
+                seed.0 = typed;
+            }
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        seed.0 /= 10;
+    }
+}
+
+pub fn refresh_seed_text(seed: Res<EndlessSeed>, mut text_query: Query<(&mut Text, Ref<SeedText>)>) {
+    for (mut text, seed_text) in text_query.iter_mut() {
+        if seed.is_changed() || seed_text.is_added() {
+            text.0 = seed.0.to_string();
+        }
+    }
+}
+
+/// Gets the generator and material ready, with the chunks around the start in place before the
+/// Form falls onto them.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_terrain(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    gltf_assets: Res<Assets<Gltf>>,
+    scene_handle: Res<SceneHandle>,
+    current_level: Res<CurrentLevel>,
+    placements: Res<CheckpointPlacements>,
+    mut terrain: ResMut<EndlessTerrain>,
+) {
+    let Some(seed) = current_level.level.seed else {
+        *terrain = EndlessTerrain::default();
+        return;
+    };
+
+    // the same surface as the glTF's terrain
+    let material = gltf_assets
+        .get(&scene_handle.handle)
+        .and_then(|scenes_gltf| scenes_gltf.named_materials.get("surface"))
+        .cloned()
+        .unwrap_or_else(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::BLACK,
+                metallic: 0.34,
+                perceptual_roughness: 0.5,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            })
+        });
+    *terrain = EndlessTerrain {
+        generator: Some(PassGenerator::new(seed)),
+        material,
+        first_checkpoint: placements.0.len() + current_level.level.checkpoints.len(),
+    };
+
+    bevy::log::info!("Generating the endless pass from seed {}", seed);
+    let spawn_x = current_level.level.spawn.x;
+    update_chunks(&mut commands, &mut meshes, &terrain, spawn_x, spawn_x, &[]);
+}
+
+/// The endless pass has no end ahead, and no way back past the start.
+pub fn extend_bounds(current_level: Res<CurrentLevel>, mut layout: ResMut<LevelLayout>) {
+    if current_level.level.seed.is_none() {
+        return;
+    }
+    let back = FIRST_CHUNK as f32 * CHUNK_LENGTH;
+    layout.bounds = vec![
+        Transform::from_xyz(back + BOUNDS_LENGTH, 0.0, 0.0).with_scale(Vec3::new(BOUNDS_LENGTH, BOUNDS_HEIGHT, HALF_WIDTH)),
+    ];
+}
+
+pub fn spawn_seed_hud(mut commands: Commands, asset_server: Res<AssetServer>, current_level: Res<CurrentLevel>) {
+    let Some(seed) = current_level.level.seed else {
+        return;
+    };
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    // in the bottom left, where other levels have their map
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(10.),
+            ..default()
+        },
+        Text(format!("endless pass, seed {}", seed)),
+        TextFont {
+            font: font_handle,
+            font_size: 16.,
+            ..default()
+        },
+        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        ClearOfJoystick,
+        InGame,
+    ));
+}
+
+pub fn stream_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    terrain: Res<EndlessTerrain>,
+    respawn_point: Res<RespawnPoint>,
+    form_query: Query<&Transform, With<Form>>,
+    chunks: Query<(Entity, &EndlessChunk)>,
+) {
+    let Ok(form_transform) = form_query.single() else {
+        return;
+    };
+    let spawned: Vec<_> = chunks.iter().collect();
+    update_chunks(
+        &mut commands,
+        &mut meshes,
+        &terrain,
+        form_transform.translation.x,
+        respawn_point.0.translation.x,
+        &spawned,
+    );
+}
+
+/// Spawns the chunks missing around `x` and despawns the ones too far from it, except the one
+/// under `respawn_x` so there's always ground to respawn on.
+fn update_chunks(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    terrain: &EndlessTerrain,
+    x: f32,
+    respawn_x: f32,
+    spawned: &[(Entity, &EndlessChunk)],
+) {
+    let Some(generator) = terrain.generator else {
+        return;
+    };
+    let current = (x / CHUNK_LENGTH).floor() as i32;
+    let respawn_chunk = ((respawn_x / CHUNK_LENGTH).floor() as i32).max(FIRST_CHUNK);
+    let wanted = (current - CHUNKS_BEHIND).max(FIRST_CHUNK)..=(current + CHUNKS_AHEAD).max(FIRST_CHUNK);
+
+    for (entity, chunk) in spawned {
+        if !wanted.contains(&chunk.0) && chunk.0 != respawn_chunk {
+            commands.entity(*entity).despawn();
+        }
+    }
+    let respawn_outside = (!wanted.contains(&respawn_chunk)).then_some(respawn_chunk);
+    for index in wanted.clone().chain(respawn_outside) {
+        if !spawned.iter().any(|(_, chunk)| chunk.0 == index) {
+            spawn_chunk(commands, meshes, terrain, &generator, index);
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    terrain: &EndlessTerrain,
+    generator: &PassGenerator,
+    index: i32,
+) {
+    let (mesh, collider) = generator.chunk(index);
+    let center_x = (index as f32 + 0.5) * CHUNK_LENGTH;
+    let center_z = generator.center(center_x);
+
+    commands.spawn((
+        Mesh3d(meshes.add(mesh)),
+        MeshMaterial3d(terrain.material.clone()),
+        Transform::from_xyz(center_x, 0.0, 0.0),
+        RigidBody::Fixed,
+        collider,
+        ActiveEvents::COLLISION_EVENTS,
+        EndlessChunk(index),
+        InGame,
+        children![lights::unshadowed_fallback_light(Vec3::new(0.0, LIGHT_HEIGHT, center_z), LIGHT_RANGE)],
+    ));
+
+    // one checkpoint per chunk past the start, after the level's own
+    if index > 0 {
+        let position = Vec3::new(center_x, generator.height(center_x, center_z) + CHECKPOINT_HEIGHT, center_z);
+        commands.spawn((
+            Transform::from_translation(position),
+            Checkpoint(terrain.first_checkpoint + index as usize - 1),
+            EndlessChunk(index),
+            InGame,
+        ));
+    }
+}
+
+pub fn reset_terrain(mut terrain: ResMut<EndlessTerrain>) {
+    *terrain = EndlessTerrain::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The chunk's vertex positions and collider heights.
+    fn chunk_data(generator: &PassGenerator, index: i32) -> (Vec<[f32; 3]>, Vec<f32>) {
+        let (mesh, collider) = generator.chunk(index);
+        let positions = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|values| values.as_float3())
+            .expect("chunk mesh has positions")
+            .to_vec();
+        let heights = collider.as_heightfield().expect("chunk collider is a heightfield").heights().to_vec();
+        (positions, heights)
+    }
+
+    #[test]
+    fn same_seed_makes_the_same_pass() {
+        let (a, b) = (PassGenerator::new(42), PassGenerator::new(42));
+        for x in [START_X, 0.0, 123.4, FINISH_X] {
+            assert_eq!(a.center(x), b.center(x));
+            for z in [-HALF_WIDTH, -7.5, 0.0, 31.0] {
+                assert_eq!(a.height(x, z), b.height(x, z));
+            }
+        }
+        for index in [FIRST_CHUNK, 0, 7] {
+            assert_eq!(chunk_data(&a, index), chunk_data(&b, index));
+        }
+
+        let (first, second) = (level(42), level(42));
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.spawn, second.spawn);
+        assert_eq!(first.start, second.start);
+        assert_eq!(first.finish, second.finish);
+        assert_eq!(first.gates, second.gates);
+        assert_eq!(seed_of(&first.id), Some(42));
+    }
+
+    #[test]
+    fn different_seeds_make_different_passes() {
+        let (a, b) = (PassGenerator::new(42), PassGenerator::new(43));
+        assert_ne!(chunk_data(&a, 3), chunk_data(&b, 3));
+        assert_ne!(level(42).gates, level(43).gates);
+    }
+}
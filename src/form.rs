@@ -1,17 +1,182 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Action, ActionState};
+use crate::loading::LoadingAssets;
+use crate::setup::{AppState, PauseState};
 
 // Movement and Form implementation draws _heavily_ from the player/ship/controller in the
 // blender_bevy_top_down_space_shooter, very cool project!
 // https://github.com/sdfgeoff/blender_bevy_top_down_space_shooter
 
+pub struct FormPlugin;
+
+impl Plugin for FormPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<FormConfig>()
+            .init_asset_loader::<FormConfigLoader>()
+            .init_resource::<FormPreset>()
+            .insert_resource(Movements::default())
+            .add_systems(OnEnter(AppState::Loading), load_config)
+            .add_systems(Update, (
+                reload_config.run_if(in_state(AppState::Running)),
+            ))
+            // once per physics tick, so a run can be recorded and replayed tick for tick
+            .add_systems(FixedUpdate, (
+                get_movement.run_if(in_state(PauseState::Playing)),
+                apply_movement
+                    .after(get_movement)
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(PauseState::Playing)),
+            ));
+    }
+}
+
 #[derive(Default, Component, Debug)]
 pub struct Form {
     pub thrust: Vec3,
     pub drag: Vec3,
+    pub push: f32,
+    pub turn: f32,
+    pub lift: f32,
+}
+
+impl Form {
+    pub fn from_config(config: &FormConfig) -> Self {
+        Self {
+            thrust: config.thrust,
+            drag: config.drag,
+            push: config.push,
+            turn: config.turn,
+            lift: config.lift,
+        }
+    }
+}
+
+/// How the ghost feels, loaded from `assets/form/<preset>.form.ron`.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormConfig {
+    pub thrust: Vec3,
+    pub drag: Vec3,
+    pub gravity_scale: f32,
+    pub collider_radius: f32,
+    pub push: f32,
+    pub turn: f32,
+    pub lift: f32,
+}
+
+impl Default for FormConfig {
+    fn default() -> Self {
+        // nothing special about these values, just played around until it felt like a ghost
+        Self {
+            thrust: Vec3::new(200.0, 100.0, 200.0),
+            drag: Vec3::new(250.0, 300.0, 250.0),
+            gravity_scale: 3.0,
+            collider_radius: 2.3,
+            push: 30.0,
+            turn: 20.0,
+            lift: 90.0,
+        }
+    }
 }
 
 #[derive(Debug)]
+pub enum FormConfigLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for FormConfigLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read form config: {e}"),
+            Self::Ron(e) => write!(f, "could not parse form config: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FormConfigLoaderError {}
+
+#[derive(Default)]
+pub struct FormConfigLoader;
+
+impl AssetLoader for FormConfigLoader {
+    type Asset = FormConfig;
+    type Settings = ();
+    type Error = FormConfigLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<FormConfig, FormConfigLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(FormConfigLoaderError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(FormConfigLoaderError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["form.ron"]
+    }
+}
+
+/// Which `assets/form/*.form.ron` preset to load. Insert before adding the plugin to pick
+/// something other than "floaty".
+#[derive(Resource, Debug, Clone)]
+pub struct FormPreset(pub String);
+
+impl Default for FormPreset {
+    fn default() -> Self {
+        Self("floaty".to_string())
+    }
+}
+
+#[derive(Resource)]
+pub struct FormConfigHandle {
+    pub handle: Handle<FormConfig>,
+}
+
+pub fn load_config(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    preset: Res<FormPreset>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    let handle = asset_server.load(format!("form/{}.form.ron", preset.0));
+    loading_assets.track(&handle, "ghost tuning");
+    commands.insert_resource(FormConfigHandle { handle });
+}
+
+/// Picks up edits to the active preset while the game runs (needs the `hot_reload` feature).
+pub fn reload_config(
+    mut events: MessageReader<AssetEvent<FormConfig>>,
+    config_handle: Res<FormConfigHandle>,
+    configs: Res<Assets<FormConfig>>,
+    mut form_query: Query<(&mut Form, &mut GravityScale, &mut Collider)>,
+) {
+    for event in events.read() {
+        if !event.is_modified(&config_handle.handle) {
+            continue;
+        }
+        if let Some(config) = configs.get(&config_handle.handle) {
+            for (mut form, mut gravity_scale, mut collider) in form_query.iter_mut() {
+                *form = Form::from_config(config);
+                *gravity_scale = GravityScale(config.gravity_scale);
+                *collider = Collider::ball(config.collider_radius);
+            }
+            bevy::log::info!("Form config reloaded: {:?}", config);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Movement {
     PushForward(f32),
     PushBackward(f32),
@@ -43,49 +208,52 @@ impl Movement {
 }
 
 #[derive(Default, Component, Debug, Resource)]
-pub struct Movements(Vec<Movement>);
+pub struct Movements(pub Vec<Movement>);
 
-pub fn get_movement(mut query: Query<&mut Movements>, keys: Res<ButtonInput<KeyCode>>) {
-    for mut movements in query.iter_mut() {
+pub fn get_movement(mut query: Query<(&mut Movements, &Form)>, actions: Res<ActionState>) {
+    for (mut movements, form) in query.iter_mut() {
         movements.0.clear();
-        let push_factor = 30.0;
-        let turn_factor = 20.0;
-        if keys.pressed(KeyCode::KeyW) || keys.pressed(KeyCode::ArrowUp) {
-            movements.0.push(Movement::PushForward(push_factor))
+        let push_factor = form.push;
+        let turn_factor = form.turn;
+        if actions.pressed(Action::Forward) {
+            movements.0.push(Movement::PushForward(push_factor * actions.value(Action::Forward)))
         }
-        if keys.pressed(KeyCode::KeyS) || keys.pressed(KeyCode::ArrowDown) {
-            movements.0.push(Movement::PushBackward(push_factor))
+        if actions.pressed(Action::Backward) {
+            movements.0.push(Movement::PushBackward(push_factor * actions.value(Action::Backward)))
         }
-        if keys.pressed(KeyCode::KeyA) {
-            movements.0.push(Movement::PushLeft(push_factor))
+        if actions.pressed(Action::StrafeLeft) {
+            movements.0.push(Movement::PushLeft(push_factor * actions.value(Action::StrafeLeft)))
         }
-        if keys.pressed(KeyCode::KeyD) {
-            movements.0.push(Movement::PushRight(push_factor))
+        if actions.pressed(Action::StrafeRight) {
+            movements.0.push(Movement::PushRight(push_factor * actions.value(Action::StrafeRight)))
         }
 
-        if keys.pressed(KeyCode::ArrowLeft) {
-            movements.0.push(Movement::TurnLeft(turn_factor))
+        if actions.pressed(Action::TurnLeft) {
+            movements.0.push(Movement::TurnLeft(turn_factor * actions.value(Action::TurnLeft)))
         }
-        if keys.pressed(KeyCode::ArrowRight) {
-            movements.0.push(Movement::TurnRight(turn_factor))
+        if actions.pressed(Action::TurnRight) {
+            movements.0.push(Movement::TurnRight(turn_factor * actions.value(Action::TurnRight)))
         }
 
-        if keys.pressed(KeyCode::Space) {
-            movements.0.push(Movement::Lift(90.0))
+        if actions.pressed(Action::Lift) {
+            movements.0.push(Movement::Lift(form.lift * actions.value(Action::Lift)))
         }
     }
 }
 
+/// Steers by the Form's `Transform`, which rapier writes back on every tick. Its
+/// `GlobalTransform` only catches up once a frame, a frame that runs several ticks would steer
+/// all but the first with a stale rotation and replays would depend on the framerate.
 pub fn apply_movement(
     mut form_query: Query<(
         &Movements,
         &Form,
-        &GlobalTransform,
+        &Transform,
         &mut ExternalForce,
         &Velocity,
     )>,
 ) {
-    if let Ok((movements, form, global_transform, mut rb_forces, rb_velocities)) =
+    if let Ok((movements, form, transform, mut rb_forces, rb_velocities)) =
         form_query.single_mut()
     {
         let mut forces = Vec3::new(0.0, 0.0, 0.0);
@@ -96,7 +264,7 @@ pub fn apply_movement(
             torques += movement.as_ang_vec() * form.thrust;
         }
 
-        let local_to_global = global_transform.to_matrix();
+        let local_to_global = transform.to_matrix();
         forces = local_to_global.transform_vector3(forces);
         torques = local_to_global.transform_vector3(torques);
 
@@ -109,23 +277,3 @@ pub fn apply_movement(
         rb_forces.torque = torques;
     }
 }
-
-pub fn wrap_movement(mut form_query: Query<(&Form, &mut Transform)>) {
-    if let Ok((_form, mut transform)) = form_query.single_mut() {
-        let max_terrain_coord = 50.0;
-        let min_terrain_coord = -50.0;
-        let current_x = transform.translation.x;
-        let current_z = transform.translation.z;
-
-        // starting coords
-        // -40.0, 20.0, 0.0
-
-        if current_x > max_terrain_coord
-            || current_z > max_terrain_coord
-            || current_x < min_terrain_coord
-            || current_z < min_terrain_coord
-        {
-            transform.translation = Vec3::new(0.0, 20.0, 0.0);
-        }
-    }
-}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use bevy::{gltf::Gltf, prelude::*, scene::SceneInstanceReady};
+use serde::{Deserialize, Serialize};
+
+use crate::form::Form;
+use crate::levels::CurrentLevel;
+use crate::race::{self, RaceTimer, RunFinished};
+use crate::scenes::SceneHandle;
+use crate::setup::{AppState, InGame, PauseState};
+use crate::storage;
+
+// Race against yourself: the Form's pose is sampled every physics tick while the clock runs,
+// and the fastest run's trajectory on each level is saved. A see-through copy of the `FORM`
+// scene, with no collider, replays it tick for tick next to the player.
+
+const GHOST_ALPHA: f32 = 0.35;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BestTrajectory>()
+            .init_resource::<CurrentTrajectory>()
+            .add_systems(OnEnter(AppState::Running), (
+                load_best_trajectory,
+                spawn_ghost.after(load_best_trajectory),
+            ))
+            .add_systems(FixedUpdate, (
+                follow_trajectory
+                    .after(race::detect_start_finish)
+                    .run_if(in_state(PauseState::Playing)),
+                save_best_trajectory
+                    .after(follow_trajectory)
+                    .run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_trajectory,
+            ));
+    }
+}
+
+/// The Form's translation and rotation on each physics tick of a run.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Trajectory {
+    pub ticks: Vec<(Vec3, Quat)>,
+}
+
+/// The trajectory of the fastest run on the current level, saved as `ghost_<level id>.ron`.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct BestTrajectory {
+    pub time: Option<Duration>,
+    pub trajectory: Trajectory,
+}
+
+impl BestTrajectory {
+    pub const STORAGE_KEY: &'static str = "ghost";
+
+    pub fn save(&self, current_level: &CurrentLevel) {
+        storage::save(&current_level.level.storage_key(Self::STORAGE_KEY), self);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct CurrentTrajectory(pub Trajectory);
+
+/// The see-through copy of the Form following the best run.
+#[derive(Component)]
+pub struct Ghost;
+
+pub fn load_best_trajectory(mut best: ResMut<BestTrajectory>, current_level: Res<CurrentLevel>) {
+    *best = storage::load(&current_level.level.storage_key(BestTrajectory::STORAGE_KEY)).unwrap_or_default();
+}
+
+pub fn spawn_ghost(
+    mut commands: Commands,
+    gltf_assets: Res<Assets<Gltf>>,
+    scene_handle: Res<SceneHandle>,
+    best: Res<BestTrajectory>,
+) {
+    let Some(&(translation, rotation)) = best.trajectory.ticks.first() else {
+        return;
+    };
+    let Some(scenes_gltf) = gltf_assets.get(&scene_handle.handle) else {
+        return;
+    };
+
+    commands
+        .spawn((
+            SceneRoot(scenes_gltf.named_scenes["FORM"].clone()),
+            Transform::from_translation(translation).with_rotation(rotation),
+            Ghost,
+            InGame,
+        ))
+        .observe(make_translucent);
+}
+
+/// Gives the ghost's meshes their own see-through copies of the materials, the player's Form
+/// shares the originals.
+fn make_translucent(
+    ready: On<SceneInstanceReady>,
+    children: Query<&Children>,
+    mut mesh_materials: Query<&mut MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for entity in children.iter_descendants(ready.entity) {
+        let Ok(mut mesh_material) = mesh_materials.get_mut(entity) else {
+            continue;
+        };
+        let Some(mut material) = materials.get(&mesh_material.0).cloned() else {
+            continue;
+        };
+        material.base_color.set_alpha(GHOST_ALPHA);
+        material.alpha_mode = AlphaMode::Blend;
+        mesh_material.0 = materials.add(material);
+    }
+}
+
+/// Samples the Form and moves the ghost to the best run's pose at the same tick.
+pub fn follow_trajectory(
+    timer: Res<RaceTimer>,
+    best: Res<BestTrajectory>,
+    mut current: ResMut<CurrentTrajectory>,
+    form_query: Query<&Transform, (With<Form>, Without<Ghost>)>,
+    mut ghost_query: Query<&mut Transform, With<Ghost>>,
+) {
+    if !timer.running {
+        return;
+    }
+    let Ok(form_transform) = form_query.single() else {
+        return;
+    };
+
+    // the clock was (re)started this tick
+    if timer.elapsed.is_zero() {
+        current.0.ticks.clear();
+    }
+    current.0.ticks.push((form_transform.translation, form_transform.rotation));
+
+    let tick = current.0.ticks.len() - 1;
+    if let Some(&(translation, rotation)) = best.trajectory.ticks.get(tick) {
+        for mut ghost_transform in ghost_query.iter_mut() {
+            ghost_transform.translation = translation;
+            ghost_transform.rotation = rotation;
+        }
+    }
+}
+
+pub fn save_best_trajectory(
+    mut finished: MessageReader<RunFinished>,
+    current: Res<CurrentTrajectory>,
+    mut best: ResMut<BestTrajectory>,
+    current_level: Res<CurrentLevel>,
+) {
+    for run in finished.read() {
+        if run.personal_best {
+            *best = BestTrajectory {
+                time: Some(run.time),
+                trajectory: current.0.clone(),
+            };
+            best.save(&current_level);
+            bevy::log::info!("Saved ghost of {} ticks", current.0.ticks.len());
+        }
+    }
+}
+
+pub fn reset_trajectory(mut current: ResMut<CurrentTrajectory>) {
+    current.0.ticks.clear();
+}
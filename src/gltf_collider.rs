@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+use bevy::{
+    ecs::system::SystemParam,
+    gltf::{Gltf, GltfMesh, GltfNode},
+    mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+// Colliders from glTF meshes. `GltfColliders` looks up a mesh by name, gathers the triangles of
+// all of its primitives (indexed or not) and places them where the mesh's node sits in the
+// scene, so a collider on the scene's root lines up with what's drawn. The geometry then
+// becomes one of the `ColliderShape`s. The `TERRAIN` collider is built this way, and any prop
+// can be.
+
+/// What kind of collider to build from a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColliderShape {
+    /// Every triangle, as modeled. Only for fixed bodies, it has no inside.
+    #[default]
+    Trimesh,
+    /// The smallest convex shape around the whole mesh.
+    ConvexHull,
+    /// Convex pieces approximating the mesh, slower to build but fine on moving bodies.
+    ConvexDecomposition,
+    /// One convex hull per primitive, for meshes already modeled in convex parts.
+    Compound,
+}
+
+/// Triangles of a mesh in scene space.
+#[derive(Debug, Clone, Default)]
+pub struct MeshGeometry {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[u32; 3]>,
+    /// Which of the `vertices` each primitive brought.
+    pub primitives: Vec<Range<usize>>,
+}
+
+impl MeshGeometry {
+    /// Gathers every primitive of `gltf_mesh`, moved by `transform`. Primitives that aren't
+    /// triangle lists or have no positions are skipped with a warning.
+    pub fn from_gltf_mesh(gltf_mesh: &GltfMesh, meshes: &Assets<Mesh>, transform: &Transform) -> Self {
+        let mut geometry = Self::default();
+        for primitive in gltf_mesh.primitives.iter() {
+            let Some(mesh) = meshes.get(&primitive.mesh) else {
+                bevy::log::warn!("Primitive {} of mesh {} isn't loaded", primitive.index, gltf_mesh.name);
+                continue;
+            };
+            if let Err(reason) = geometry.push_mesh(mesh, transform) {
+                bevy::log::warn!("Skipping primitive {} of mesh {}: {}", primitive.index, gltf_mesh.name, reason);
+            }
+        }
+        geometry
+    }
+
+    fn push_mesh(&mut self, mesh: &Mesh, transform: &Transform) -> Result<(), String> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return Err(format!("{:?} isn't a triangle list", mesh.primitive_topology()));
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return Err("positions are missing or not Float32x3".to_string());
+        };
+        // without indices every three vertices are a triangle
+        let indices: Vec<u32> = match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let first = self.vertices.len();
+        self.vertices
+            .extend(positions.iter().map(|&position| transform.transform_point(Vec3::from(position))));
+        self.triangles.extend(
+            indices
+                .chunks_exact(3)
+                .map(|chunk| [chunk[0], chunk[1], chunk[2]].map(|index| first as u32 + index)),
+        );
+        self.primitives.push(first..self.vertices.len());
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn collider(&self, shape: ColliderShape) -> Option<Collider> {
+        if self.is_empty() {
+            return None;
+        }
+        match shape {
+            ColliderShape::Trimesh => match Collider::trimesh(self.vertices.clone(), self.triangles.clone()) {
+                Ok(collider) => Some(collider),
+                Err(e) => {
+                    bevy::log::warn!("Failed to build a trimesh collider: {:?}", e);
+                    None
+                }
+            },
+            ColliderShape::ConvexHull => Collider::convex_hull(&self.vertices),
+            ColliderShape::ConvexDecomposition => Some(Collider::convex_decomposition(&self.vertices, &self.triangles)),
+            ColliderShape::Compound => {
+                let hulls: Vec<_> = self
+                    .primitives
+                    .iter()
+                    .filter_map(|range| Collider::convex_hull(&self.vertices[range.clone()]))
+                    .map(|hull| (Vec3::ZERO, Quat::IDENTITY, hull))
+                    .collect();
+                (!hulls.is_empty()).then(|| Collider::compound(hulls))
+            }
+        }
+    }
+}
+
+/// The assets needed to turn glTF meshes into colliders.
+#[derive(SystemParam)]
+pub struct GltfColliders<'w> {
+    gltf_nodes: Res<'w, Assets<GltfNode>>,
+    gltf_meshes: Res<'w, Assets<GltfMesh>>,
+    meshes: Res<'w, Assets<Mesh>>,
+}
+
+impl GltfColliders<'_> {
+    /// The mesh named `name`, placed like the first node that shows it.
+    pub fn geometry(&self, gltf: &Gltf, name: &str) -> Option<MeshGeometry> {
+        let Some(mesh_handle) = gltf.named_meshes.get(name) else {
+            bevy::log::warn!("No mesh named {} in the glTF", name);
+            return None;
+        };
+        let gltf_mesh = self.gltf_meshes.get(mesh_handle)?;
+        let transform = gltf
+            .nodes
+            .iter()
+            .find(|node_handle| {
+                self.gltf_nodes
+                    .get(*node_handle)
+                    .is_some_and(|node| node.mesh.as_ref() == Some(mesh_handle))
+            })
+            .map(|node_handle| scene_transform(gltf, &self.gltf_nodes, node_handle))
+            .unwrap_or_default();
+
+        Some(MeshGeometry::from_gltf_mesh(gltf_mesh, &self.meshes, &transform))
+    }
+
+    pub fn collider(&self, gltf: &Gltf, name: &str, shape: ColliderShape) -> Option<Collider> {
+        self.geometry(gltf, name)?.collider(shape)
+    }
+}
+
+/// Where a node sits relative to its scene's root, through all of its parents.
+pub fn scene_transform(gltf: &Gltf, gltf_nodes: &Assets<GltfNode>, node_handle: &Handle<GltfNode>) -> Transform {
+    let local = gltf_nodes.get(node_handle).map(|node| node.transform).unwrap_or_default();
+    let parent = gltf.nodes.iter().find(|candidate| {
+        gltf_nodes
+            .get(*candidate)
+            .is_some_and(|node| node.children.contains(node_handle))
+    });
+    match parent {
+        Some(parent_handle) => scene_transform(gltf, gltf_nodes, parent_handle).mul_transform(local),
+        None => local,
+    }
+}
+
+/// Where every node whose name starts with `prefix` sits, sorted by name so they keep their order from run to run.
+pub fn named_node_transforms(gltf: &Gltf, gltf_nodes: &Assets<GltfNode>, prefix: &str) -> Vec<Transform> {
+    let mut names: Vec<_> = gltf.named_nodes.keys().filter(|name| name.starts_with(prefix)).collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| scene_transform(gltf, gltf_nodes, &gltf.named_nodes[name]))
+        .collect()
+}
@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraController, LookTransform};
+
+use crate::settings::Settings;
+use crate::storage;
+
+// Player intent is read through actions instead of raw keys, so bindings can be changed from the
+// controls screen and saved between runs. `form::get_movement` only ever looks at `ActionState`.
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(
+                storage::load::<Bindings>(Bindings::STORAGE_KEY)
+                    .map(Bindings::with_missing_defaults)
+                    .unwrap_or_default(),
+            )
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, (
+                update_actions.after(bevy::input::InputSystems),
+            ))
+            .add_systems(Update, (
+                gamepad_orbit,
+            ));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Forward,
+    Backward,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Lift,
+    /// Switches between the camera modes.
+    Camera,
+    /// Enters and leaves photo mode.
+    Photo,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Forward,
+        Action::Backward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Lift,
+        Action::Camera,
+        Action::Photo,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Backward => "backward",
+            Self::StrafeLeft => "strafe left",
+            Self::StrafeRight => "strafe right",
+            Self::TurnLeft => "turn left",
+            Self::TurnRight => "turn right",
+            Self::Lift => "lift",
+            Self::Camera => "camera",
+            Self::Photo => "photo mode",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis, AxisDirection),
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Key(key) => key_label(*key),
+            Self::GamepadButton(button) => gamepad_button_label(*button),
+            Self::GamepadAxis(axis, direction) => gamepad_axis_label(*axis, *direction),
+        }
+    }
+
+    pub fn is_key(&self) -> bool {
+        matches!(self, Self::Key(_))
+    }
+
+    /// How far this binding is held, 0.0 to 1.0. Sticks and triggers report partial values.
+    fn value(&self, keys: &ButtonInput<KeyCode>, gamepads: &Query<&Gamepad>) -> f32 {
+        match self {
+            Self::Key(key) => {
+                if keys.pressed(*key) { 1.0 } else { 0.0 }
+            }
+            Self::GamepadButton(button) => gamepads
+                .iter()
+                .map(|gamepad| {
+                    let analog = gamepad.get(*button).unwrap_or(0.0);
+                    if analog > 0.0 {
+                        analog
+                    } else if gamepad.pressed(*button) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                })
+                .fold(0.0, f32::max),
+            Self::GamepadAxis(axis, direction) => gamepads
+                .iter()
+                .map(|gamepad| {
+                    let value = gamepad.get(*axis).unwrap_or(0.0);
+                    match direction {
+                        AxisDirection::Positive => value.max(0.0),
+                        AxisDirection::Negative => (-value).max(0.0),
+                    }
+                })
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+fn gamepad_button_label(button: GamepadButton) -> String {
+    match button {
+        GamepadButton::LeftTrigger => "left bumper".to_string(),
+        GamepadButton::RightTrigger => "right bumper".to_string(),
+        GamepadButton::LeftTrigger2 => "left trigger".to_string(),
+        GamepadButton::RightTrigger2 => "right trigger".to_string(),
+        _ => format!("pad {button:?}").to_lowercase(),
+    }
+}
+
+fn gamepad_axis_label(axis: GamepadAxis, direction: AxisDirection) -> String {
+    let (stick, positive, negative) = match axis {
+        GamepadAxis::LeftStickX => ("left stick", "right", "left"),
+        GamepadAxis::LeftStickY => ("left stick", "up", "down"),
+        GamepadAxis::RightStickX => ("right stick", "right", "left"),
+        GamepadAxis::RightStickY => ("right stick", "up", "down"),
+        _ => return format!("pad {axis:?} {direction:?}").to_lowercase(),
+    };
+    match direction {
+        AxisDirection::Positive => format!("{stick} {positive}"),
+        AxisDirection::Negative => format!("{stick} {negative}"),
+    }
+}
+
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::ArrowUp => "↑".to_string(),
+        KeyCode::ArrowDown => "↓".to_string(),
+        KeyCode::ArrowLeft => "←".to_string(),
+        KeyCode::ArrowRight => "→".to_string(),
+        KeyCode::Space => "space bar".to_string(),
+        _ => {
+            let name = format!("{key:?}");
+            name.strip_prefix("Key")
+                .or_else(|| name.strip_prefix("Digit"))
+                .unwrap_or(&name)
+                .to_lowercase()
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Bindings(pub BTreeMap<Action, Vec<Binding>>);
+
+impl Bindings {
+    pub const STORAGE_KEY: &'static str = "bindings";
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn label(&self, action: Action) -> String {
+        self.get(action)
+            .iter()
+            .map(Binding::label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn key_label(&self, action: Action) -> String {
+        self.get(action)
+            .iter()
+            .filter(|binding| binding.is_key())
+            .map(Binding::label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn save(&self) {
+        storage::save(Self::STORAGE_KEY, self);
+    }
+
+    /// Binds actions added since these bindings were saved to their defaults.
+    pub fn with_missing_defaults(mut self) -> Self {
+        for (action, defaults) in Self::default().0 {
+            self.0.entry(action).or_insert(defaults);
+        }
+        self
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use AxisDirection::*;
+        use KeyCode::*;
+        Self(BTreeMap::from([
+            (Action::Forward, vec![
+                Binding::Key(KeyW),
+                Binding::Key(ArrowUp),
+                Binding::GamepadAxis(GamepadAxis::LeftStickY, Positive),
+            ]),
+            (Action::Backward, vec![
+                Binding::Key(KeyS),
+                Binding::Key(ArrowDown),
+                Binding::GamepadAxis(GamepadAxis::LeftStickY, Negative),
+            ]),
+            (Action::StrafeLeft, vec![
+                Binding::Key(KeyA),
+                Binding::GamepadButton(GamepadButton::LeftTrigger),
+            ]),
+            (Action::StrafeRight, vec![
+                Binding::Key(KeyD),
+                Binding::GamepadButton(GamepadButton::RightTrigger),
+            ]),
+            (Action::TurnLeft, vec![
+                Binding::Key(ArrowLeft),
+                Binding::GamepadAxis(GamepadAxis::LeftStickX, Negative),
+            ]),
+            (Action::TurnRight, vec![
+                Binding::Key(ArrowRight),
+                Binding::GamepadAxis(GamepadAxis::LeftStickX, Positive),
+            ]),
+            (Action::Lift, vec![
+                Binding::Key(Space),
+                Binding::GamepadButton(GamepadButton::LeftTrigger2),
+                Binding::GamepadButton(GamepadButton::RightTrigger2),
+            ]),
+            (Action::Camera, vec![
+                Binding::Key(KeyC),
+                Binding::GamepadButton(GamepadButton::North),
+            ]),
+            (Action::Photo, vec![
+                Binding::Key(KeyP),
+                Binding::GamepadButton(GamepadButton::Select),
+            ]),
+        ]))
+    }
+}
+
+/// How strongly each action is held this frame, from 0.0 (not at all) to 1.0.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    previous: HashMap<Action, f32>,
+}
+
+impl ActionState {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) > 0.0
+    }
+
+    /// Held this frame but not the one before.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !self.previous.contains_key(&action)
+    }
+
+    /// Holds `action` at least `value` this frame, for input sources outside of `Bindings`.
+    pub fn press(&mut self, action: Action, value: f32) {
+        if value <= 0.0 {
+            return;
+        }
+        let held = self.values.entry(action).or_insert(0.0);
+        *held = held.max(value.min(1.0));
+    }
+}
+
+pub fn update_actions(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    bindings: Res<Bindings>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.previous = std::mem::take(&mut actions.values);
+    for action in Action::ALL {
+        let value = bindings
+            .get(action)
+            .iter()
+            .map(|binding| binding.value(&keys, &gamepads))
+            .fold(0.0, f32::max)
+            .min(1.0);
+        if value > 0.0 {
+            actions.values.insert(action, value);
+        }
+    }
+}
+
+/// Swings the camera `delta` radians around its target: x is yaw, y is pitch.
+pub fn orbit_look(look: &mut LookTransform, delta: Vec2) {
+    let offset = look.eye - look.target;
+    let yawed = Quat::from_rotation_y(-delta.x) * offset;
+    let right = yawed.cross(Vec3::Y).normalize_or_zero();
+    let pitched = Quat::from_axis_angle(right, delta.y) * yawed;
+    // don't pitch over the top or under the bottom of the orbit
+    let orbit = if pitched.normalize_or_zero().y.abs() < 0.98 { pitched } else { yawed };
+    look.eye = look.target + orbit;
+}
+
+/// Orbits the camera around its target with the right stick, alongside the mouse controls
+/// of the orbit controller.
+pub fn gamepad_orbit(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    gamepads: Query<&Gamepad>,
+    mut cameras: Query<(&OrbitCameraController, &mut LookTransform)>,
+) {
+    let stick = gamepads
+        .iter()
+        .map(Gamepad::right_stick)
+        .fold(Vec2::ZERO, |a, b| if b.length() > a.length() { b } else { a });
+    if stick == Vec2::ZERO {
+        return;
+    }
+
+    let speed = 2.0 * time.delta_secs();
+    for (controller, mut look) in cameras.iter_mut() {
+        if controller.enabled {
+            orbit_look(&mut look, stick * speed * settings.orbit_scale());
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use bevy::{
+    gltf::{Gltf, GltfNode},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::form::Form;
+use crate::gltf_collider;
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, InGame, PauseState};
+
+// Gameplay objects placed in blender: give an empty a `gameplay` custom property and the glTF
+// exporter writes it to the node's `extras` ("Custom Properties" checked), where it's read when
+// the level spawns.
+//
+// - `spawn`: where the Form starts a run, facing the empty's +x
+// - `bounds`: a box the Form has to stay inside, the empty's scale is its half size (a cube
+//   empty of size 1 shows it). With several the Form can be in any of them
+// - `kill_plane`: the Form respawns once it's below the empty's xz plane
+// - `checkpoint`: one more checkpoint, an `order` number sets where it falls in the course
+// - `trigger`: a sensor box sized like `bounds`, sends `TriggerEntered` with its `trigger`
+//   property (or the node's name) when the Form enters it
+//
+// Whatever a level leaves out comes from the manifest: its spawn, its bounds on x and z, and a
+// floor far below the pass.
+
+/// The kill plane's height when the level has none.
+const DEFAULT_FLOOR: f32 = -20.0;
+/// Half the height of the manifest's bounds, which only limit x and z.
+const DEFAULT_BOUNDS_HEIGHT: f32 = 1000.0;
+
+pub struct LevelNodesPlugin;
+
+impl Plugin for LevelNodesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LevelLayout>()
+            .add_message::<TriggerEntered>()
+            .add_systems(OnEnter(AppState::Running), (
+                read_level_layout,
+                spawn_triggers.after(read_level_layout),
+            ))
+            .add_systems(FixedUpdate, (
+                enter_triggers.after(PhysicsSet::Writeback).run_if(in_state(PauseState::Playing)),
+            ));
+    }
+}
+
+/// The custom properties of a node, as the exporter writes them.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct NodeProperties {
+    gameplay: Option<String>,
+    /// Course order of a checkpoint, lower first.
+    order: Option<i32>,
+    /// What a trigger is called in `TriggerEntered`.
+    trigger: Option<String>,
+}
+
+/// Where things are in the current level, read from its glTF when it spawns.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LevelLayout {
+    pub spawn: Transform,
+    /// Boxes from -1 to 1 moved by each transform, the Form has to be in one of them.
+    pub bounds: Vec<Transform>,
+    /// Planes through each transform facing its +y, the Form has to be above all of them.
+    pub kill_planes: Vec<Transform>,
+    /// In course order.
+    pub checkpoints: Vec<Transform>,
+    /// Boxes like `bounds`, with their names.
+    pub triggers: Vec<(String, Transform)>,
+}
+
+impl LevelLayout {
+    /// Whether the Form can be at `point`, outside of the bounds or below a kill plane it respawns.
+    pub fn contains(&self, point: Vec3) -> bool {
+        let in_bounds = self.bounds.iter().any(|volume| in_box(volume, point));
+        let above_kill_planes = self
+            .kill_planes
+            .iter()
+            .all(|plane| plane.up().dot(point - plane.translation) >= 0.0);
+        in_bounds && above_kill_planes
+    }
+}
+
+fn in_box(volume: &Transform, point: Vec3) -> bool {
+    let local = volume.compute_affine().inverse().transform_point3(point);
+    local.abs().max_element() <= 1.0
+}
+
+/// A sensor box placed in blender.
+#[derive(Component, Debug, Clone)]
+pub struct Trigger(pub String);
+
+/// Sent on the tick the Form enters a trigger, with the trigger's name.
+#[derive(Message, Debug, Clone)]
+pub struct TriggerEntered(pub String);
+
+pub fn read_level_layout(
+    gltf_assets: Res<Assets<Gltf>>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    current_level: Res<CurrentLevel>,
+    mut layout: ResMut<LevelLayout>,
+) {
+    let level = &current_level.level;
+    *layout = LevelLayout::default();
+    let mut spawns = Vec::new();
+    let mut checkpoints = Vec::new();
+
+    if let Some(level_gltf) = gltf_assets.get(&current_level.gltf) {
+        for node_handle in level_gltf.nodes.iter() {
+            let Some(node) = gltf_nodes.get(node_handle) else {
+                continue;
+            };
+            let Some(extras) = &node.extras else {
+                continue;
+            };
+            let properties = match serde_json::from_str::<NodeProperties>(&extras.value) {
+                Ok(properties) => properties,
+                Err(e) => {
+                    bevy::log::warn!("Could not read the custom properties of {}: {}", node.name, e);
+                    continue;
+                }
+            };
+            let Some(gameplay) = properties.gameplay else {
+                continue;
+            };
+
+            let transform = gltf_collider::scene_transform(level_gltf, &gltf_nodes, node_handle);
+            match gameplay.as_str() {
+                "spawn" => spawns.push(transform),
+                "bounds" => layout.bounds.push(transform),
+                "kill_plane" => layout.kill_planes.push(transform),
+                "checkpoint" => checkpoints.push((properties.order.unwrap_or(i32::MAX), node.name.clone(), transform)),
+                "trigger" => layout
+                    .triggers
+                    .push((properties.trigger.unwrap_or_else(|| node.name.clone()), transform)),
+                other => bevy::log::warn!("{} has an unknown gameplay property {}", node.name, other),
+            }
+        }
+    }
+
+    if spawns.len() > 1 {
+        bevy::log::warn!("{} has {} spawn points, using the first", level.name, spawns.len());
+    }
+    // the empty's scale would scale the Form
+    layout.spawn = spawns
+        .first()
+        .map(|spawn| Transform::from_translation(spawn.translation).with_rotation(spawn.rotation))
+        .unwrap_or(Transform::from_translation(level.spawn));
+    if layout.bounds.is_empty() {
+        layout
+            .bounds
+            .push(Transform::from_scale(Vec3::new(level.bounds, DEFAULT_BOUNDS_HEIGHT, level.bounds)));
+    }
+    if layout.kill_planes.is_empty() {
+        layout.kill_planes.push(Transform::from_xyz(0.0, DEFAULT_FLOOR, 0.0));
+    }
+    // unnumbered checkpoints go last, by name
+    checkpoints.sort_by(|(order, name, _), (other_order, other_name, _)| (order, name).cmp(&(other_order, other_name)));
+    layout.checkpoints = checkpoints.into_iter().map(|(_, _, transform)| transform).collect();
+
+    bevy::log::info!(
+        "Level {}: {} bounds, {} kill planes, {} checkpoints and {} triggers",
+        level.name,
+        layout.bounds.len(),
+        layout.kill_planes.len(),
+        layout.checkpoints.len(),
+        layout.triggers.len()
+    );
+}
+
+pub fn spawn_triggers(mut commands: Commands, layout: Res<LevelLayout>) {
+    for (name, transform) in layout.triggers.iter() {
+        // the collider is scaled with the transform
+        commands.spawn((
+            *transform,
+            Collider::cuboid(1.0, 1.0, 1.0),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            Trigger(name.clone()),
+            InGame,
+        ));
+    }
+}
+
+pub fn enter_triggers(
+    mut collisions: MessageReader<CollisionEvent>,
+    form_query: Query<Entity, With<Form>>,
+    triggers: Query<&Trigger>,
+    mut entered: MessageWriter<TriggerEntered>,
+) {
+    let Ok(form_entity) = form_query.single() else {
+        return;
+    };
+    for collision in collisions.read() {
+        let CollisionEvent::Started(a, b, _) = collision else {
+            continue;
+        };
+        let other = if *a == form_entity {
+            *b
+        } else if *b == form_entity {
+            *a
+        } else {
+            continue;
+        };
+        if let Ok(trigger) = triggers.get(other) {
+            bevy::log::info!("Trigger {} entered", trigger.0);
+            entered.write(TriggerEntered(trigger.0.clone()));
+        }
+    }
+}
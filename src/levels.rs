@@ -0,0 +1,348 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    gltf::Gltf,
+    input_focus::tab_navigation::TabGroup,
+    prelude::*,
+};
+use bevy_kira_audio::AudioSource;
+use serde::Deserialize;
+
+use crate::endless::{self, EndlessSeed};
+use crate::loading::LoadingAssets;
+use crate::setup::{self, spawn_text_button, AppState, ButtonActionQuery};
+
+// The passes to pick from, listed in `assets/levels/manifest.levels.ron`. Once the manifest is
+// in, every level's glTF and music track is loaded alongside the rest, so picking one on the
+// level select screen starts it right away. The endless pass is always on offer below them, made
+// from a seed instead of a glTF, with its music loaded alongside the manifest. The chosen level is
+// `CurrentLevel` for as long as it runs, everything spawned for it is `InGame` and goes when the
+// run ends.
+
+const MANIFEST_PATH: &str = "levels/manifest.levels.ron";
+
+pub struct LevelsPlugin;
+
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<LevelManifest>()
+            .init_asset_loader::<LevelManifestLoader>()
+            .init_resource::<Levels>()
+            .init_resource::<EndlessMusic>()
+            .add_systems(OnEnter(AppState::Loading), load_manifest)
+            .add_systems(Update, (
+                load_levels.before(setup::check_loaded).run_if(in_state(AppState::Loading)),
+            ))
+            .add_systems(OnEnter(AppState::LevelSelect), (
+                spawn_level_select,
+            ))
+            .add_systems(Update, (
+                handle_level_select_buttons.run_if(in_state(AppState::LevelSelect)),
+            ))
+            .add_systems(OnExit(AppState::LevelSelect), (
+                cleanup_level_select,
+            ));
+    }
+}
+
+/// One level as the manifest describes it. `id`, `name` and `gltf` are required, the rest have
+/// defaults.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Level {
+    /// Used in file names, best times and ghosts are saved per level.
+    pub id: String,
+    /// Shown on the level select screen.
+    pub name: String,
+    /// The glTF with the level in it, relative to `assets/`.
+    pub gltf: String,
+    /// The named scene that's spawned as the level.
+    #[serde(default = "default_terrain")]
+    pub terrain_scene: String,
+    /// The named mesh the terrain collider is built from.
+    #[serde(default = "default_terrain")]
+    pub terrain_mesh: String,
+    /// Where the Form starts a run, unless the glTF has a spawn empty, see `level_nodes`.
+    #[serde(default)]
+    pub spawn: Vec3,
+    /// The level spans -`bounds` to `bounds` on x and z, past that the Form respawns. Bounds
+    /// empties in the glTF take its place.
+    #[serde(default = "default_bounds")]
+    pub bounds: f32,
+    /// The music track, relative to `assets/`.
+    #[serde(default = "default_music")]
+    pub music: String,
+    /// Where the race starts, unless the glTF has a `START` node. Both lines face +x.
+    #[serde(default)]
+    pub start: Option<Vec3>,
+    /// Where the race finishes, unless the glTF has a `FINISH` node.
+    #[serde(default)]
+    pub finish: Option<Vec3>,
+    /// Where limbo gates stand facing +x, on top of the glTF's `GATE...` nodes.
+    #[serde(default)]
+    pub gates: Vec<Vec3>,
+    /// Checkpoints in course order, before the glTF's own.
+    #[serde(default)]
+    pub checkpoints: Vec<Vec3>,
+    /// Generate the terrain from this seed instead of loading `gltf`, see `endless`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_terrain() -> String {
+    "TERRAIN".to_string()
+}
+
+fn default_bounds() -> f32 {
+    50.0
+}
+
+fn default_music() -> String {
+    "audio/overworld.ogg".to_string()
+}
+
+/// No level in particular: no id, no glTF and no course, with the manifest's defaults for the
+/// rest.
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            gltf: String::new(),
+            terrain_scene: default_terrain(),
+            terrain_mesh: default_terrain(),
+            spawn: Vec3::ZERO,
+            bounds: default_bounds(),
+            music: default_music(),
+            start: None,
+            finish: None,
+            gates: Vec::new(),
+            checkpoints: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+impl Level {
+    /// The storage key for something saved per level, like `times_limbo_pass`.
+    pub fn storage_key(&self, key: &str) -> String {
+        format!("{}_{}", key, self.id)
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct LevelManifest {
+    pub levels: Vec<Level>,
+}
+
+#[derive(Debug)]
+pub enum LevelManifestLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for LevelManifestLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read level manifest: {e}"),
+            Self::Ron(e) => write!(f, "could not parse level manifest: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LevelManifestLoaderError {}
+
+#[derive(Default)]
+pub struct LevelManifestLoader;
+
+impl AssetLoader for LevelManifestLoader {
+    type Asset = LevelManifest;
+    type Settings = ();
+    type Error = LevelManifestLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<LevelManifest, LevelManifestLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(LevelManifestLoaderError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(LevelManifestLoaderError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["levels.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelManifestHandle {
+    pub handle: Handle<LevelManifest>,
+    /// Whether the levels in it are loading, so it's read once.
+    pub read: bool,
+}
+
+/// A level with the handles of its assets.
+#[derive(Debug, Clone)]
+pub struct LoadedLevel {
+    pub level: Level,
+    pub gltf: Handle<Gltf>,
+    pub music: Handle<AudioSource>,
+}
+
+/// Every level in the manifest, in its order.
+#[derive(Resource, Default, Debug)]
+pub struct Levels(pub Vec<LoadedLevel>);
+
+/// The endless pass's music, loaded with the manifest whether or not a level there shares it.
+#[derive(Resource, Default, Debug)]
+pub struct EndlessMusic(pub Handle<AudioSource>);
+
+/// The level being played, chosen on the level select screen.
+#[derive(Resource, Debug, Clone, Deref)]
+pub struct CurrentLevel(pub LoadedLevel);
+
+#[derive(Component)]
+pub struct LevelSelectScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum LevelSelectButton {
+    Play(usize),
+    Endless,
+    Back,
+}
+
+pub fn load_manifest(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut levels: ResMut<Levels>,
+    mut endless_music: ResMut<EndlessMusic>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    let handle = asset_server.load(MANIFEST_PATH);
+    loading_assets.track(&handle, "levels");
+    endless_music.0 = asset_server.load(endless::level(0).music);
+    loading_assets.track_optional(&endless_music.0, "music");
+    commands.insert_resource(LevelManifestHandle { handle, read: false });
+    // loaded again below, retrying requests levels that failed anew
+    levels.0.clear();
+}
+
+/// Loads every level's assets once the manifest is in, before `check_loaded` could move on
+/// without them.
+pub fn load_levels(
+    asset_server: Res<AssetServer>,
+    manifests: Res<Assets<LevelManifest>>,
+    mut manifest_handle: ResMut<LevelManifestHandle>,
+    mut levels: ResMut<Levels>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    if manifest_handle.read {
+        return;
+    }
+    let Some(manifest) = manifests.get(&manifest_handle.handle) else {
+        return;
+    };
+    manifest_handle.read = true;
+    if manifest.levels.is_empty() {
+        bevy::log::warn!("The level manifest lists no levels");
+    }
+
+    for level in manifest.levels.iter() {
+        let gltf = if level.seed.is_some() {
+            Handle::default()
+        } else {
+            let gltf = asset_server.load(&level.gltf);
+            loading_assets.track(&gltf, level.name.clone());
+            gltf
+        };
+        let music = asset_server.load(&level.music);
+        loading_assets.track_optional(&music, "music");
+        levels.0.push(LoadedLevel {
+            level: level.clone(),
+            gltf,
+            music,
+        });
+    }
+    bevy::log::info!("Loading {} levels", levels.0.len());
+}
+
+pub fn spawn_level_select(mut commands: Commands, asset_server: Res<AssetServer>, levels: Res<Levels>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(16.),
+                ..default()
+            },
+            LevelSelectScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("choose a pass".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+            for (index, loaded) in levels.0.iter().enumerate() {
+                spawn_text_button(parent, &loaded.level.name, font_handle.clone(), 32., LevelSelectButton::Play(index));
+            }
+            spawn_text_button(parent, "endless pass", font_handle.clone(), 32., LevelSelectButton::Endless);
+            endless::spawn_seed_row(parent, font_handle.clone());
+            spawn_text_button(parent, "back", font_handle.clone(), 24., LevelSelectButton::Back);
+        });
+}
+
+pub fn handle_level_select_buttons(
+    mut commands: Commands,
+    mut interaction_query: ButtonActionQuery<LevelSelectButton>,
+    levels: Res<Levels>,
+    endless_music: Res<EndlessMusic>,
+    seed: Res<EndlessSeed>,
+    mut state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, level_select_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match level_select_button {
+                LevelSelectButton::Play(index) => {
+                    if let Some(loaded) = levels.0.get(*index) {
+                        bevy::log::info!("Starting level {}", loaded.level.name);
+                        commands.insert_resource(CurrentLevel(loaded.clone()));
+                        state.set(AppState::Running);
+                    }
+                }
+                LevelSelectButton::Endless => {
+                    let level = endless::level(seed.0);
+                    bevy::log::info!("Starting the endless pass with seed {}", seed.0);
+                    commands.insert_resource(CurrentLevel(LoadedLevel {
+                        level,
+                        gltf: Handle::default(),
+                        music: endless_music.0.clone(),
+                    }));
+                    state.set(AppState::Running);
+                }
+                LevelSelectButton::Back => state.set(AppState::Menu),
+            }
+        }
+    }
+}
+
+pub fn cleanup_level_select(mut commands: Commands, query: Query<Entity, With<LevelSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
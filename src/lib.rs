@@ -0,0 +1,101 @@
+use bevy::{app::PluginGroupBuilder, asset::AssetMetaCheck, prelude::*, window::PresentMode};
+use bevy_kira_audio::AudioPlugin;
+use bevy_rapier3d::prelude::*;
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraPlugin, LookTransformPlugin};
+
+pub mod camera;
+pub mod checkpoint;
+pub mod endless;
+pub mod form;
+pub mod ghost;
+pub mod gltf_collider;
+pub mod input;
+pub mod level_nodes;
+pub mod levels;
+pub mod lights;
+pub mod limbo;
+pub mod loading;
+pub mod minimap;
+pub mod photo;
+pub mod race;
+pub mod replay;
+pub mod scenes;
+pub mod settings;
+pub mod setup;
+pub mod storage;
+pub mod terrain;
+pub mod theme;
+pub mod touch;
+
+pub use crate::setup::AppState;
+
+/// Everything limbo pass needs on top of bevy's `DefaultPlugins`: physics, audio, cameras,
+/// the app state machine and the game's own systems.
+pub struct LimboPassPlugin;
+
+impl Plugin for LimboPassPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(LookTransformPlugin)
+            .add_plugins(OrbitCameraPlugin::default())
+            .add_plugins(AudioPlugin)
+            // physics steps by a fixed 1/64s on `FixedUpdate` (bevy's default rate), the same
+            // inputs always give the same run, see `replay`
+            .insert_resource(TimestepMode::Fixed {
+                dt: 1.0 / 64.0,
+                substeps: 1,
+            })
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule())
+            // .add_plugins(RapierDebugRenderPlugin::default())
+            .add_plugins((
+                loading::LoadingPlugin,
+                input::InputPlugin,
+                settings::SettingsPlugin,
+                touch::TouchPlugin,
+                setup::SetupPlugin,
+                camera::CameraPlugin,
+                scenes::ScenesPlugin,
+                theme::ThemePlugin,
+                form::FormPlugin,
+                levels::LevelsPlugin,
+                lights::LightsPlugin,
+            ))
+            // bevy takes at most 15 plugins in one tuple
+            .add_plugins((
+                level_nodes::LevelNodesPlugin,
+                checkpoint::CheckpointPlugin,
+                limbo::LimboPlugin,
+                race::RacePlugin,
+                replay::ReplayPlugin,
+                ghost::GhostPlugin,
+                photo::PhotoPlugin,
+                minimap::MinimapPlugin,
+                endless::EndlessPlugin,
+            ));
+    }
+}
+
+/// `DefaultPlugins` configured the way limbo pass expects when it owns the window.
+/// Apps embedding [`LimboPassPlugin`] can bring their own instead.
+pub fn default_plugins() -> PluginGroupBuilder {
+    DefaultPlugins
+        .set(AssetPlugin {
+            meta_check: AssetMetaCheck::Never,
+            ..default()
+        })
+        .set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "limbo pass".to_string(),
+                // Fifo doesn't work on WASM, use AutoVsync instead
+                present_mode: if cfg!(target_arch = "wasm32") {
+                    PresentMode::AutoVsync
+                } else {
+                    PresentMode::Fifo
+                },
+                // Make window fill the viewport on WASM
+                fit_canvas_to_parent: cfg!(target_arch = "wasm32"),
+                ..default()
+            }),
+            ..default()
+        })
+}
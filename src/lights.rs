@@ -0,0 +1,210 @@
+use bevy::{camera::visibility::RenderLayers, prelude::*, scene::SceneInstanceReady};
+use bevy_rapier3d::prelude::*;
+
+use crate::camera::FORM_LAYER;
+use crate::form::Form;
+use crate::settings::ShadowCaster;
+use crate::setup::{AppState, InGame};
+
+// The level's lights come from the glTF: lights exported with the `TERRAIN` scene
+// (KHR_lights_punctual, "Punctual Lights" in blender's exporter) are spawned with it, scaled by
+// `GltfLightPolicy`. Only when the scene has no lights at all does the pass get lit by the
+// fallback point lights below.
+//
+// The Form carries its own green foot light, glowing brighter the closer it floats to the
+// ground and flickering faster the faster it rises or falls.
+
+const FALLBACK_COLOR: Color = Color::srgb_u8(0xAB, 0x69, 0xE7);
+const FALLBACK_INTENSITY: f32 = 10_000_000.0;
+const FALLBACK_POSITIONS: [Vec3; 4] = [
+    Vec3::new(-40.0, 15.0, 0.0),
+    Vec3::new(40.0, 15.0, 0.0),
+    Vec3::new(0.0, 15.0, -40.0),
+    Vec3::new(0.0, 15.0, 40.0),
+];
+
+const FOOT_COLOR: Color = Color::srgb_u8(0x70, 0xFF, 0x00);
+const FOOT_INTENSITY: f32 = 400_000.0;
+const FOOT_RANGE: f32 = 12.0;
+/// Below the Form's origin, about where its hem is.
+const FOOT_OFFSET: Vec3 = Vec3::new(0.0, -2.0, 0.0);
+/// Past this far above the ground the foot light is out.
+const FOOT_REACH: f32 = 14.0;
+/// Pulses per second hovering still, and added per m/s of vertical speed.
+const PULSE_RATE: f32 = 0.5;
+const PULSE_RATE_PER_SPEED: f32 = 0.6;
+/// How deep the pulse dips at full speed, the vertical speed that counts as full.
+const PULSE_DEPTH: f32 = 0.5;
+const PULSE_FULL_SPEED: f32 = 10.0;
+
+pub struct LightsPlugin;
+
+impl Plugin for LightsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GltfLightPolicy>()
+            .add_systems(Update, (
+                update_foot_light.run_if(in_state(AppState::Running)),
+            ));
+    }
+}
+
+/// How lights from the glTF are brought into the game. Insert before adding `LightsPlugin` to
+/// change it.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GltfLightPolicy {
+    /// Multiplies the intensity (illuminance for sun lights) the exporter wrote. Blender's
+    /// "standard" lighting mode exports watts as far dimmer than the fallback lights, raise
+    /// this rather than cranking the lights in blender.
+    pub intensity_scale: f32,
+    /// Whether spot and sun lights cast shadows, as long as the shadow setting isn't off. Point
+    /// lights always follow the setting.
+    pub shadows: bool,
+}
+
+impl Default for GltfLightPolicy {
+    fn default() -> Self {
+        Self {
+            intensity_scale: 1.0,
+            shadows: true,
+        }
+    }
+}
+
+/// The lights that stand in when the glTF has none.
+#[derive(Component)]
+pub struct FallbackLight;
+
+/// The light under the Form, a child of it.
+#[derive(Component)]
+pub struct FootLight;
+
+pub fn foot_light() -> impl Bundle {
+    (
+        PointLight {
+            color: FOOT_COLOR,
+            range: FOOT_RANGE,
+            intensity: FOOT_INTENSITY,
+            ..default()
+        },
+        Transform::from_translation(FOOT_OFFSET),
+        FootLight,
+    )
+}
+
+/// Applies the policy to the lights that came with the scene, or spawns the fallbacks.
+pub fn use_gltf_lights(
+    ready: On<SceneInstanceReady>,
+    mut commands: Commands,
+    policy: Res<GltfLightPolicy>,
+    children: Query<&Children>,
+    mut point_lights: Query<&mut PointLight>,
+    mut spot_lights: Query<&mut SpotLight>,
+    mut directional_lights: Query<&mut DirectionalLight>,
+) {
+    // on the Form's layer too, so it keeps casting shadows
+    let layers = RenderLayers::layer(0).with(FORM_LAYER);
+    let mut found = 0;
+
+    for entity in children.iter_descendants(ready.entity) {
+        if let Ok(mut light) = point_lights.get_mut(entity) {
+            light.intensity *= policy.intensity_scale;
+            commands.entity(entity).insert((layers.clone(), ShadowCaster));
+        } else if let Ok(mut light) = spot_lights.get_mut(entity) {
+            light.intensity *= policy.intensity_scale;
+            light.shadows_enabled = policy.shadows;
+            commands.entity(entity).insert(layers.clone());
+            if policy.shadows {
+                commands.entity(entity).insert(ShadowCaster);
+            }
+        } else if let Ok(mut light) = directional_lights.get_mut(entity) {
+            light.illuminance *= policy.intensity_scale;
+            light.shadows_enabled = policy.shadows;
+            commands.entity(entity).insert(layers.clone());
+            if policy.shadows {
+                commands.entity(entity).insert(ShadowCaster);
+            }
+        } else {
+            continue;
+        }
+        found += 1;
+    }
+
+    if found > 0 {
+        bevy::log::info!("Lit by {} lights from the glTF", found);
+    } else {
+        bevy::log::info!("No lights in the glTF, using the fallback lights");
+        for position in FALLBACK_POSITIONS {
+            commands.spawn((fallback_light(position), InGame));
+        }
+    }
+}
+
+/// One of the purple lights a level without lights of its own gets.
+pub fn fallback_light(position: Vec3) -> impl Bundle {
+    (
+        PointLight {
+            color: FALLBACK_COLOR,
+            range: 500.0,
+            intensity: FALLBACK_INTENSITY,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_translation(position),
+        // on the Form's layer too, so it keeps casting shadows
+        RenderLayers::layer(0).with(FORM_LAYER),
+        ShadowCaster,
+        FallbackLight,
+    )
+}
+
+/// A fallback light reaching only `range` and casting no shadows, whatever the settings, for where
+/// many of them are lit at once.
+pub fn unshadowed_fallback_light(position: Vec3, range: f32) -> impl Bundle {
+    (
+        PointLight {
+            color: FALLBACK_COLOR,
+            range,
+            intensity: FALLBACK_INTENSITY,
+            shadows_enabled: false,
+            ..default()
+        },
+        Transform::from_translation(position),
+        RenderLayers::layer(0).with(FORM_LAYER),
+        FallbackLight,
+    )
+}
+
+/// Dims the foot light with the Form's height above the terrain and pulses it with its
+/// vertical speed.
+pub fn update_foot_light(
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    form_query: Query<(&GlobalTransform, &Velocity), With<Form>>,
+    mut foot_lights: Query<&mut PointLight, With<FootLight>>,
+    mut phase: Local<f32>,
+) {
+    let Ok((form_transform, velocity)) = form_query.single() else {
+        return;
+    };
+
+    let ground_distance = rapier_context.single().ok().and_then(|context| {
+        context.cast_ray(
+            form_transform.translation(),
+            Vec3::NEG_Y,
+            FOOT_REACH,
+            true,
+            QueryFilter::only_fixed().exclude_sensors(),
+        )
+    });
+    let proximity = ground_distance.map_or(0.0, |(_, distance)| 1.0 - distance / FOOT_REACH);
+
+    let vertical_speed = velocity.linvel.y.abs();
+    *phase = (*phase + time.delta_secs() * (PULSE_RATE + vertical_speed * PULSE_RATE_PER_SPEED)).fract();
+    let depth = PULSE_DEPTH * (vertical_speed / PULSE_FULL_SPEED).min(1.0);
+    let pulse = 1.0 - depth * (0.5 + 0.5 * (*phase * std::f32::consts::TAU).sin());
+
+    for mut light in foot_lights.iter_mut() {
+        light.intensity = FOOT_INTENSITY * proximity * pulse;
+    }
+}
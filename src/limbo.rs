@@ -0,0 +1,354 @@
+use bevy::{
+    gltf::{Gltf, GltfNode},
+    input_focus::tab_navigation::TabGroup,
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::form::Form;
+use crate::gltf_collider;
+use crate::levels::CurrentLevel;
+use crate::setup::{spawn_text_button, AppState, ButtonActionQuery, InGame, PauseState};
+
+// The limbo: gates along the pass with a bar the ghost has to get under. Each gate has two
+// sensors, the bar itself (touching it ends the run) and the gap below it (leaving it on the far
+// side is a clean pass). Once every gate is passed the round is over and all the bars drop.
+
+const GATE_HALF_WIDTH: f32 = 6.0;
+const POST_HEIGHT: f32 = 12.0;
+const POST_RADIUS: f32 = 0.3;
+const BAR_HALF_THICKNESS: f32 = 0.15;
+const START_HEIGHT: f32 = 9.0;
+const HEIGHT_STEP: f32 = 0.8;
+/// A little more than the ghost is tall, the bar never gets lower than this.
+const MIN_HEIGHT: f32 = 5.2;
+
+pub struct LimboPlugin;
+
+impl Plugin for LimboPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LimboCourse>()
+            .init_resource::<Limbo>()
+            .add_systems(OnEnter(AppState::Running), (
+                spawn_gates,
+                spawn_limbo_hud,
+            ))
+            .add_systems(FixedUpdate, (
+                detect_gates.after(PhysicsSet::Writeback).run_if(in_state(PauseState::Playing)),
+                // the lowered bars reach rapier at the next tick's sync, before it steps again
+                lower_bars.after(detect_gates).run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(Update, (
+                update_limbo_hud.run_if(in_state(AppState::Running)),
+            ))
+            .add_systems(OnEnter(PauseState::Failed), (
+                spawn_failed_screen,
+            ))
+            .add_systems(Update, (
+                handle_failed_buttons.run_if(in_state(PauseState::Failed)),
+            ))
+            .add_systems(OnExit(PauseState::Failed), (
+                cleanup_failed_screen,
+            ));
+    }
+}
+
+/// Gates on every level, on top of the level's `gates` and glTF nodes named `GATE...`. Insert
+/// before adding the plugin to add some.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LimboCourse(pub Vec<Transform>);
+
+/// Score and round of the current run.
+#[derive(Resource, Debug)]
+pub struct Limbo {
+    pub round: u32,
+    pub score: u32,
+    pub bar_height: f32,
+    pub gates: usize,
+    /// Gates passed cleanly this round.
+    passed: Vec<usize>,
+}
+
+impl Default for Limbo {
+    fn default() -> Self {
+        Self {
+            round: 1,
+            score: 0,
+            bar_height: START_HEIGHT,
+            gates: 0,
+            passed: Vec::new(),
+        }
+    }
+}
+
+impl Limbo {
+    fn pass(&mut self, gate: usize) {
+        if self.passed.contains(&gate) {
+            return;
+        }
+        self.passed.push(gate);
+        self.score += self.round;
+        bevy::log::info!("Clean pass under gate {} (round {}, score {})", gate, self.round, self.score);
+
+        if self.passed.len() >= self.gates {
+            self.passed.clear();
+            self.round += 1;
+            self.bar_height = (self.bar_height - HEIGHT_STEP).max(MIN_HEIGHT);
+            bevy::log::info!("Round {}, the bar drops to {}", self.round, self.bar_height);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LimboGate(pub usize);
+
+#[derive(Component)]
+pub struct LimboBar(pub usize);
+
+#[derive(Component)]
+pub struct LimboGap(pub usize);
+
+#[derive(Component)]
+pub struct LimboText;
+
+#[derive(Component)]
+pub struct FailedScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum FailedButton {
+    TryAgain,
+    Quit,
+}
+
+fn gap_collider(bar_height: f32) -> Collider {
+    Collider::cuboid(0.5, bar_height / 2.0, GATE_HALF_WIDTH)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_gates(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    gltf_assets: Res<Assets<Gltf>>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    current_level: Res<CurrentLevel>,
+    course: Res<LimboCourse>,
+    mut limbo: ResMut<Limbo>,
+) {
+    let mut transforms = course.0.clone();
+    transforms.extend(current_level.level.gates.iter().copied().map(Transform::from_translation));
+    if let Some(scenes_gltf) = gltf_assets.get(&current_level.gltf) {
+        transforms.extend(gltf_collider::named_node_transforms(scenes_gltf, &gltf_nodes, "GATE"));
+    }
+
+    *limbo = Limbo {
+        gates: transforms.len(),
+        ..default()
+    };
+
+    let post_mesh = meshes.add(Cylinder::new(POST_RADIUS, POST_HEIGHT));
+    let bar_mesh = meshes.add(Cuboid::new(
+        BAR_HALF_THICKNESS * 2.0,
+        BAR_HALF_THICKNESS * 2.0,
+        GATE_HALF_WIDTH * 2.0,
+    ));
+    let post_material = materials.add(Color::srgb(0.6, 0.6, 0.6));
+    let bar_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.67, 0.41, 0.91),
+        emissive: LinearRgba::rgb(0.67, 0.41, 0.91),
+        ..default()
+    });
+
+    for (index, transform) in transforms.into_iter().enumerate() {
+        commands
+            .spawn((transform, Visibility::default(), LimboGate(index), InGame))
+            .with_children(|gate| {
+                for z in [-GATE_HALF_WIDTH, GATE_HALF_WIDTH] {
+                    gate.spawn((
+                        Mesh3d(post_mesh.clone()),
+                        MeshMaterial3d(post_material.clone()),
+                        Transform::from_xyz(0.0, POST_HEIGHT / 2.0, z),
+                        Collider::cylinder(POST_HEIGHT / 2.0, POST_RADIUS),
+                    ));
+                }
+                gate.spawn((
+                    Mesh3d(bar_mesh.clone()),
+                    MeshMaterial3d(bar_material.clone()),
+                    Transform::from_xyz(0.0, limbo.bar_height, 0.0),
+                    Collider::cuboid(BAR_HALF_THICKNESS, BAR_HALF_THICKNESS, GATE_HALF_WIDTH),
+                    Sensor,
+                    ActiveEvents::COLLISION_EVENTS,
+                    LimboBar(index),
+                ));
+                gate.spawn((
+                    Transform::from_xyz(0.0, limbo.bar_height / 2.0, 0.0),
+                    gap_collider(limbo.bar_height),
+                    Sensor,
+                    ActiveEvents::COLLISION_EVENTS,
+                    LimboGap(index),
+                ));
+            });
+    }
+}
+
+pub fn spawn_limbo_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    // next to the controls text in the top left
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(220.),
+            top: Val::Px(10.),
+            ..default()
+        },
+        Text::default(),
+        TextFont {
+            font: font_handle,
+            font_size: 16.,
+            ..default()
+        },
+        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        LimboText,
+        InGame,
+    ));
+}
+
+pub fn detect_gates(
+    mut collisions: MessageReader<CollisionEvent>,
+    form_query: Query<(Entity, &Transform), With<Form>>,
+    bars: Query<&LimboBar>,
+    gaps: Query<(&LimboGap, &ChildOf)>,
+    gates: Query<&Transform, With<LimboGate>>,
+    mut limbo: ResMut<Limbo>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let Ok((form_entity, form_transform)) = form_query.single() else {
+        return;
+    };
+
+    for collision in collisions.read() {
+        let (started, a, b) = match collision {
+            CollisionEvent::Started(a, b, _) => (true, *a, *b),
+            CollisionEvent::Stopped(a, b, _) => (false, *a, *b),
+        };
+        let other = if a == form_entity {
+            b
+        } else if b == form_entity {
+            a
+        } else {
+            continue;
+        };
+
+        if started && let Ok(bar) = bars.get(other) {
+            bevy::log::info!("Clipped the bar of gate {}", bar.0);
+            next_pause_state.set(PauseState::Failed);
+            return;
+        }
+
+        // only leaving the gap on the far side counts, backing out of it doesn't
+        // the gap only sits higher up in its gate, the gate tells the sides apart
+        if !started
+            && let Ok((gap, child_of)) = gaps.get(other)
+            && let Ok(gate_transform) = gates.get(child_of.parent())
+            && gate_transform.compute_affine().inverse().transform_point3(form_transform.translation).x > 0.0
+        {
+            limbo.pass(gap.0);
+        }
+    }
+}
+
+pub fn lower_bars(
+    limbo: Res<Limbo>,
+    mut bars: Query<&mut Transform, (With<LimboBar>, Without<LimboGap>)>,
+    mut gaps: Query<(&mut Transform, &mut Collider), With<LimboGap>>,
+) {
+    if !limbo.is_changed() {
+        return;
+    }
+    for mut transform in bars.iter_mut() {
+        transform.translation.y = limbo.bar_height;
+    }
+    for (mut transform, mut collider) in gaps.iter_mut() {
+        transform.translation.y = limbo.bar_height / 2.0;
+        *collider = gap_collider(limbo.bar_height);
+    }
+}
+
+pub fn update_limbo_hud(limbo: Res<Limbo>, mut text_query: Query<&mut Text, With<LimboText>>) {
+    if !limbo.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.0 = format!("limbo\n--------------------\nround  {}\nscore  {}", limbo.round, limbo.score);
+    }
+}
+
+pub fn spawn_failed_screen(mut commands: Commands, asset_server: Res<AssetServer>, limbo: Res<Limbo>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(16.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.04, 0.05, 0.09, 0.8)),
+            GlobalZIndex(1),
+            FailedScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("the ghost clipped the bar".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+            ));
+            parent.spawn((
+                Text(format!("round {}, score {}", limbo.round, limbo.score)),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 24.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+            spawn_text_button(parent, "try again", font_handle.clone(), 32., FailedButton::TryAgain);
+            spawn_text_button(parent, "quit to menu", font_handle.clone(), 32., FailedButton::Quit);
+        });
+}
+
+pub fn handle_failed_buttons(
+    mut interaction_query: ButtonActionQuery<FailedButton>,
+    mut state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, failed_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match failed_button {
+                FailedButton::TryAgain => state.set(AppState::Restarting),
+                FailedButton::Quit => state.set(AppState::Menu),
+            }
+        }
+    }
+}
+
+pub fn cleanup_failed_screen(mut commands: Commands, query: Query<Entity, With<FailedScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
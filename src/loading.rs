@@ -0,0 +1,97 @@
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState},
+    prelude::*,
+};
+
+// Everything the game needs before the menu is registered here by whoever loads it, so the
+// loading screen can show real progress and `setup::check_loaded` knows what to wait for.
+// States are recursive, a glTF only counts as loaded once its buffers and textures are.
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingAssets>();
+    }
+}
+
+pub struct TrackedAsset {
+    pub handle: UntypedHandle,
+    pub label: String,
+    /// The game can go on without it (the theme is the only such asset).
+    pub optional: bool,
+}
+
+impl TrackedAsset {
+    pub fn state(&self, asset_server: &AssetServer) -> RecursiveDependencyLoadState {
+        asset_server
+            .get_recursive_dependency_load_state(self.handle.id())
+            .unwrap_or(RecursiveDependencyLoadState::NotLoaded)
+    }
+
+    pub fn path(&self) -> String {
+        self.handle.path().map(ToString::to_string).unwrap_or_else(|| self.label.clone())
+    }
+
+    /// 0.0 to 1.0, an asset that has loaded but still waits on dependencies counts as halfway.
+    pub fn progress(&self, asset_server: &AssetServer) -> f32 {
+        match self.state(asset_server) {
+            RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed(_) => 1.0,
+            RecursiveDependencyLoadState::Loading => {
+                if matches!(asset_server.get_load_state(self.handle.id()), Some(LoadState::Loaded)) {
+                    0.5
+                } else {
+                    0.0
+                }
+            }
+            RecursiveDependencyLoadState::NotLoaded => 0.0,
+        }
+    }
+
+    pub fn status(&self, asset_server: &AssetServer) -> &'static str {
+        match self.state(asset_server) {
+            RecursiveDependencyLoadState::NotLoaded => "waiting",
+            RecursiveDependencyLoadState::Loading => "loading",
+            RecursiveDependencyLoadState::Loaded => "loaded",
+            RecursiveDependencyLoadState::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LoadingAssets(Vec<TrackedAsset>);
+
+impl LoadingAssets {
+    pub fn track<A: Asset>(&mut self, handle: &Handle<A>, label: impl Into<String>) {
+        self.insert(handle, label.into(), false);
+    }
+
+    /// Tracks an asset the player may choose to continue without if it fails.
+    pub fn track_optional<A: Asset>(&mut self, handle: &Handle<A>, label: impl Into<String>) {
+        self.insert(handle, label.into(), true);
+    }
+
+    fn insert<A: Asset>(&mut self, handle: &Handle<A>, label: String, optional: bool) {
+        // loaders run again when retrying, the handles they get back are the same
+        if self.0.iter().any(|tracked| tracked.handle.id() == handle.id().untyped()) {
+            return;
+        }
+        self.0.push(TrackedAsset {
+            handle: handle.clone().untyped(),
+            label,
+            optional,
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TrackedAsset> {
+        self.0.iter()
+    }
+
+    /// Overall progress from 0.0 to 1.0.
+    pub fn progress(&self, asset_server: &AssetServer) -> f32 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        self.0.iter().map(|tracked| tracked.progress(asset_server)).sum::<f32>() / self.0.len() as f32
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::{
+    camera::{visibility::RenderLayers, RenderTarget, ScalingMode},
+    prelude::*,
+    render::render_resource::TextureFormat,
+};
+
+use crate::checkpoint::Checkpoint;
+use crate::form::Form;
+use crate::level_nodes::{self, LevelLayout};
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, InGame};
+use crate::touch::ClearOfJoystick;
+
+// An overhead map of the pass in the bottom left (above the joystick on touch devices): a second,
+// orthographic camera looks straight down at the terrain and renders into a texture the HUD
+// shows. The Form's position and heading, the checkpoints and the bounds the Form respawns
+// outside of are drawn over it, +x to the right.
+
+/// Size of the map on screen, in pixels.
+const MAP_SIZE: f32 = 180.0;
+/// How much more of the world than the level's bounds the map shows.
+const MAP_MARGIN: f32 = 1.1;
+const TEXTURE_SIZE: u32 = 256;
+const MARKER_LENGTH: f32 = 14.0;
+const MARKER_WIDTH: f32 = 6.0;
+const CHECKPOINT_SIZE: f32 = 8.0;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Running), (
+                spawn_minimap.after(level_nodes::read_level_layout),
+            ))
+            .add_systems(Update, (
+                add_checkpoint_markers.run_if(in_state(AppState::Running)),
+                update_form_marker.run_if(in_state(AppState::Running)),
+            ));
+    }
+}
+
+#[derive(Component)]
+pub struct MinimapCamera;
+
+/// The map itself, the markers are its children.
+#[derive(Component)]
+pub struct Minimap {
+    /// Half of the world's width the map shows.
+    pub extent: f32,
+}
+
+#[derive(Component)]
+pub struct FormMarker;
+
+/// Where a point in the world is on the map, in pixels from its top left corner.
+fn map_position(translation: Vec3, extent: f32) -> Vec2 {
+    (Vec2::new(translation.x, translation.z) + extent) / (2.0 * extent) * MAP_SIZE
+}
+
+/// Where a heading in the world points on the map, clockwise from +x like the UI turns.
+fn map_angle(rotation: Quat) -> f32 {
+    // the map's y runs along +z
+    let heading = rotation * Vec3::X;
+    heading.z.atan2(heading.x)
+}
+
+pub fn spawn_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    current_level: Res<CurrentLevel>,
+    layout: Res<LevelLayout>,
+) {
+    // a map of a fixed patch of the world can't follow the endless pass
+    if current_level.level.seed.is_some() {
+        return;
+    }
+    // wide enough for every corner of every bounds box
+    let reach = layout
+        .bounds
+        .iter()
+        .flat_map(|volume| {
+            [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+                .map(|(x, z)| volume.transform_point(Vec3::new(x, 0.0, z)))
+        })
+        .fold(1.0_f32, |reach, corner| reach.max(corner.x.abs()).max(corner.z.abs()));
+    let extent = reach * MAP_MARGIN;
+    let pixels_per_metre = MAP_SIZE / (2.0 * extent);
+    let image_handle = images.add(Image::new_target_texture(
+        TEXTURE_SIZE,
+        TEXTURE_SIZE,
+        TextureFormat::Bgra8UnormSrgb,
+    ));
+
+    // high above the middle of the pass, with -z at the top of the picture so +x is to the right
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::from(image_handle.clone()),
+            order: -1,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.04, 0.05, 0.09)),
+            ..default()
+        },
+        Projection::from(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: 2.0 * extent,
+                height: 2.0 * extent,
+            },
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_xyz(0.0, 100.0, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+        // the terrain without the Form, which gets a marker instead
+        RenderLayers::layer(0),
+        MinimapCamera,
+        InGame,
+    ));
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.),
+                bottom: Val::Px(10.),
+                width: Val::Px(MAP_SIZE),
+                height: Val::Px(MAP_SIZE),
+                ..default()
+            },
+            ImageNode::new(image_handle),
+            Minimap { extent },
+            ClearOfJoystick,
+            InGame,
+        ))
+        .with_children(|map| {
+            for volume in layout.bounds.iter() {
+                let center = map_position(volume.translation, extent);
+                let size = Vec2::new(volume.scale.x, volume.scale.z) * 2.0 * pixels_per_metre;
+                map.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(center.x - size.x / 2.0),
+                        top: Val::Px(center.y - size.y / 2.0),
+                        width: Val::Px(size.x),
+                        height: Val::Px(size.y),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::srgba(0.67, 0.41, 0.91, 0.8)),
+                    UiTransform {
+                        rotation: Rot2::radians(map_angle(volume.rotation)),
+                        ..default()
+                    },
+                ));
+            }
+            // a bar with a bright nose, pointing where the Form faces
+            map.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(MARKER_LENGTH),
+                    height: Val::Px(MARKER_WIDTH),
+                    justify_content: JustifyContent::End,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.01, 0.91, 0.94)),
+                UiTransform::default(),
+                FormMarker,
+            ))
+            .with_children(|marker| {
+                marker.spawn((
+                    Node {
+                        width: Val::Px(MARKER_WIDTH),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    BackgroundColor(Color::WHITE),
+                ));
+            });
+        });
+}
+
+pub fn add_checkpoint_markers(
+    mut commands: Commands,
+    checkpoints: Query<&Transform, Added<Checkpoint>>,
+    minimaps: Query<(Entity, &Minimap)>,
+) {
+    let Ok((minimap_entity, minimap)) = minimaps.single() else {
+        return;
+    };
+    for checkpoint_transform in checkpoints.iter() {
+        let position = map_position(checkpoint_transform.translation, minimap.extent) - CHECKPOINT_SIZE / 2.0;
+        commands.entity(minimap_entity).with_child((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                width: Val::Px(CHECKPOINT_SIZE),
+                height: Val::Px(CHECKPOINT_SIZE),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.44, 1.0, 0.0)),
+            BorderRadius::MAX,
+        ));
+    }
+}
+
+pub fn update_form_marker(
+    form_query: Query<&Transform, With<Form>>,
+    minimaps: Query<&Minimap>,
+    mut markers: Query<(&mut Node, &mut UiTransform), With<FormMarker>>,
+) {
+    let (Ok(form_transform), Ok(minimap)) = (form_query.single(), minimaps.single()) else {
+        return;
+    };
+    let position = map_position(form_transform.translation, minimap.extent);
+    let angle = map_angle(form_transform.rotation);
+
+    for (mut node, mut ui_transform) in markers.iter_mut() {
+        node.left = Val::Px(position.x - MARKER_LENGTH / 2.0);
+        node.top = Val::Px(position.y - MARKER_WIDTH / 2.0);
+        ui_transform.rotation = Rot2::radians(angle);
+    }
+}
@@ -0,0 +1,479 @@
+use bevy::{
+    camera::{visibility::RenderLayers, Exposure},
+    core_pipeline::tonemapping::Tonemapping,
+    input::mouse::AccumulatedMouseMotion,
+    input_focus::tab_navigation::TabGroup,
+    post_process::dof::{DepthOfField, DepthOfFieldMode},
+    prelude::*,
+    render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured},
+};
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraController, LookTransform};
+
+use crate::camera::{CameraMode, FORM_LAYER};
+use crate::input::{Action, ActionState};
+use crate::settings::Settings;
+use crate::setup::{spawn_text_button, ButtonActionQuery, PauseState};
+
+// Photo mode: the run freezes (physics and the clock only step while playing), the HUD goes
+// away and the camera flies free. A small panel sets exposure, tonemapping, depth of field and
+// field of view, and "take photo" saves a PNG of the window, to `photos/` on native and as a
+// download on the web. Leaving puts the camera back the way it was.
+
+/// Free camera speed in meters per second.
+const FLY_SPEED: f32 = 12.0;
+/// Radians per second at full stick or key.
+const LOOK_SPEED: f32 = 1.5;
+/// Radians per pixel of mouse movement while holding the right mouse button.
+const MOUSE_LOOK_SPEED: f32 = 0.004;
+
+pub struct PhotoPlugin;
+
+impl Plugin for PhotoPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PhotoSettings>()
+            .add_systems(Update, (
+                enter_photo_mode.run_if(in_state(PauseState::Playing)),
+                leave_photo_mode.run_if(in_state(PauseState::Photo)),
+            ))
+            .add_systems(OnEnter(PauseState::Photo), (
+                take_camera,
+                hide_hud,
+                spawn_photo_panel,
+            ))
+            .add_systems(Update, (
+                fly_camera.run_if(in_state(PauseState::Photo)),
+                handle_photo_buttons.run_if(in_state(PauseState::Photo)),
+                refresh_photo_texts.after(handle_photo_buttons).run_if(in_state(PauseState::Photo)),
+                apply_photo_settings.after(handle_photo_buttons).run_if(in_state(PauseState::Photo)),
+            ))
+            .add_systems(OnExit(PauseState::Photo), (
+                return_camera,
+                show_hud,
+                cleanup_photo_panel,
+            ));
+    }
+}
+
+/// The lens the photo camera shoots with, kept between visits to photo mode.
+#[derive(Resource, Debug, Clone)]
+pub struct PhotoSettings {
+    pub exposure: f32,
+    pub tonemapping: Tonemapping,
+    pub depth_of_field: bool,
+    pub focal_distance: f32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+}
+
+impl Default for PhotoSettings {
+    fn default() -> Self {
+        Self {
+            exposure: Exposure::default().ev100,
+            tonemapping: Tonemapping::default(),
+            depth_of_field: false,
+            focal_distance: 15.0,
+            fov: 45.0,
+        }
+    }
+}
+
+const TONEMAPPINGS: [(Tonemapping, &str); 6] = [
+    (Tonemapping::TonyMcMapface, "tony mc mapface"),
+    (Tonemapping::AgX, "agx"),
+    (Tonemapping::AcesFitted, "aces"),
+    (Tonemapping::BlenderFilmic, "filmic"),
+    (Tonemapping::ReinhardLuminance, "reinhard"),
+    (Tonemapping::None, "none"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PhotoKind {
+    Exposure,
+    Tonemapping,
+    DepthOfField,
+    FocalDistance,
+    Fov,
+}
+
+impl PhotoKind {
+    const ALL: [PhotoKind; 5] = [
+        PhotoKind::Exposure,
+        PhotoKind::Tonemapping,
+        PhotoKind::DepthOfField,
+        PhotoKind::FocalDistance,
+        PhotoKind::Fov,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Exposure => "exposure",
+            Self::Tonemapping => "tonemapping",
+            Self::DepthOfField => "depth of field",
+            Self::FocalDistance => "focus",
+            Self::Fov => "fov",
+        }
+    }
+
+    fn value(&self, photo: &PhotoSettings) -> String {
+        match self {
+            Self::Exposure => format!("{:.1} ev", photo.exposure),
+            Self::Tonemapping => TONEMAPPINGS
+                .iter()
+                .find(|(tonemapping, _)| *tonemapping == photo.tonemapping)
+                .map_or("?", |(_, label)| label)
+                .to_string(),
+            Self::DepthOfField => if photo.depth_of_field { "on" } else { "off" }.to_string(),
+            Self::FocalDistance => format!("{:.0} m", photo.focal_distance),
+            Self::Fov => format!("{:.0}°", photo.fov),
+        }
+    }
+
+    /// Steps the setting up (`step` 1) or down (`step` -1), toggles just flip.
+    fn adjust(&self, photo: &mut PhotoSettings, step: f32) {
+        match self {
+            Self::Exposure => photo.exposure = (photo.exposure + 0.5 * step).clamp(4.0, 16.0),
+            Self::Tonemapping => {
+                let index = TONEMAPPINGS
+                    .iter()
+                    .position(|(tonemapping, _)| *tonemapping == photo.tonemapping)
+                    .unwrap_or(0);
+                let next = (index as isize + step as isize).rem_euclid(TONEMAPPINGS.len() as isize);
+                photo.tonemapping = TONEMAPPINGS[next as usize].0;
+            }
+            Self::DepthOfField => photo.depth_of_field = !photo.depth_of_field,
+            Self::FocalDistance => photo.focal_distance = (photo.focal_distance + 1.0 * step).clamp(1.0, 100.0),
+            Self::Fov => photo.fov = (photo.fov + 5.0 * step).clamp(15.0, 110.0),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PhotoPanel;
+
+#[derive(Component)]
+pub struct PhotoText(PhotoKind);
+
+#[derive(Component, Clone, Copy)]
+pub enum PhotoButton {
+    Decrease(PhotoKind),
+    Increase(PhotoKind),
+    TakePhoto,
+    Back,
+}
+
+/// HUD nodes hidden for photo mode, with the visibility to give back.
+#[derive(Component)]
+pub struct HiddenForPhoto(Visibility);
+
+/// How the camera was before photo mode took it.
+#[derive(Resource)]
+pub struct CameraBeforePhoto {
+    eye: Vec3,
+    target: Vec3,
+    fov: Option<f32>,
+    exposure: Option<Exposure>,
+    tonemapping: Tonemapping,
+}
+
+pub fn enter_photo_mode(actions: Res<ActionState>, mut next_pause_state: ResMut<NextState<PauseState>>) {
+    if actions.just_pressed(Action::Photo) {
+        next_pause_state.set(PauseState::Photo);
+    }
+}
+
+pub fn leave_photo_mode(
+    actions: Res<ActionState>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if actions.just_pressed(Action::Photo) || keys.just_pressed(KeyCode::Escape) {
+        next_pause_state.set(PauseState::Playing);
+    }
+}
+
+pub fn take_camera(
+    mut commands: Commands,
+    mut cameras: Query<(
+        Entity,
+        &mut OrbitCameraController,
+        &LookTransform,
+        &Projection,
+        Option<&Exposure>,
+        &Tonemapping,
+    )>,
+    mut photo: ResMut<PhotoSettings>,
+) {
+    for (entity, mut controller, look, projection, exposure, tonemapping) in cameras.iter_mut() {
+        let fov = match projection {
+            Projection::Perspective(perspective) => Some(perspective.fov),
+            _ => None,
+        };
+        commands.insert_resource(CameraBeforePhoto {
+            eye: look.eye,
+            target: look.target,
+            fov,
+            exposure: exposure.copied(),
+            tonemapping: *tonemapping,
+        });
+        controller.enabled = false;
+        // the Form is in the picture, even from first person
+        commands.entity(entity).insert(RenderLayers::layer(0).with(FORM_LAYER));
+    }
+    // so the lens is applied on entering
+    photo.set_changed();
+}
+
+pub fn return_camera(
+    mut commands: Commands,
+    before: Option<Res<CameraBeforePhoto>>,
+    mut mode: ResMut<CameraMode>,
+    mut cameras: Query<(Entity, &mut LookTransform, &mut Projection, &mut Tonemapping)>,
+) {
+    let Some(before) = before else {
+        return;
+    };
+    for (entity, mut look, mut projection, mut tonemapping) in cameras.iter_mut() {
+        look.eye = before.eye;
+        look.target = before.target;
+        if let (Projection::Perspective(perspective), Some(fov)) = (&mut *projection, before.fov) {
+            perspective.fov = fov;
+        }
+        *tonemapping = before.tonemapping;
+        let mut camera = commands.entity(entity);
+        camera.remove::<DepthOfField>();
+        match before.exposure {
+            Some(exposure) => camera.insert(exposure),
+            None => camera.remove::<Exposure>(),
+        };
+    }
+    commands.remove_resource::<CameraBeforePhoto>();
+    // hands the controller and render layers back to the camera mode
+    mode.set_changed();
+}
+
+pub fn hide_hud(
+    mut commands: Commands,
+    mut hud: Query<(Entity, &mut Visibility), (With<Node>, Without<ChildOf>, Without<PhotoPanel>)>,
+) {
+    for (entity, mut visibility) in hud.iter_mut() {
+        commands.entity(entity).insert(HiddenForPhoto(*visibility));
+        *visibility = Visibility::Hidden;
+    }
+}
+
+pub fn show_hud(mut commands: Commands, mut hidden: Query<(Entity, &HiddenForPhoto, &mut Visibility)>) {
+    for (entity, hidden_for_photo, mut visibility) in hidden.iter_mut() {
+        *visibility = hidden_for_photo.0;
+        commands.entity(entity).remove::<HiddenForPhoto>();
+    }
+}
+
+pub fn spawn_photo_panel(mut commands: Commands, asset_server: Res<AssetServer>, photo: Res<PhotoSettings>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    // tucked into the bottom right, out of the way of the shot
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                bottom: Val::Px(10.),
+                padding: UiRect::all(Val::Px(12.)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::End,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.04, 0.05, 0.09, 0.6)),
+            GlobalZIndex(1),
+            PhotoPanel,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            for kind in PhotoKind::ALL {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text(kind.label().to_string()),
+                            TextFont {
+                                font: font_handle.clone(),
+                                font_size: 16.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                            Node {
+                                width: Val::Px(140.),
+                                ..default()
+                            },
+                        ));
+                        spawn_text_button(row, "<", font_handle.clone(), 16., PhotoButton::Decrease(kind));
+                        row.spawn((
+                            Text(kind.value(&photo)),
+                            TextFont {
+                                font: font_handle.clone(),
+                                font_size: 16.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                            TextLayout::new_with_justify(Justify::Center),
+                            Node {
+                                width: Val::Px(150.),
+                                ..default()
+                            },
+                            PhotoText(kind),
+                        ));
+                        spawn_text_button(row, ">", font_handle.clone(), 16., PhotoButton::Increase(kind));
+                    });
+            }
+
+            parent.spawn(Node {
+                height: Val::Px(8.),
+                ..default()
+            });
+            spawn_text_button(parent, "take photo", font_handle.clone(), 20., PhotoButton::TakePhoto);
+            spawn_text_button(parent, "back", font_handle.clone(), 20., PhotoButton::Back);
+        });
+}
+
+pub fn handle_photo_buttons(
+    mut commands: Commands,
+    mut interaction_query: ButtonActionQuery<PhotoButton>,
+    mut photo: ResMut<PhotoSettings>,
+    mut panels: Query<&mut Visibility, With<PhotoPanel>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut taken: Local<u32>,
+) {
+    for (interaction, photo_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match photo_button {
+                PhotoButton::Decrease(kind) => kind.adjust(&mut photo, -1.0),
+                PhotoButton::Increase(kind) => kind.adjust(&mut photo, 1.0),
+                PhotoButton::TakePhoto => {
+                    // the panel stays out of the frame being captured
+                    for mut visibility in panels.iter_mut() {
+                        *visibility = Visibility::Hidden;
+                    }
+                    *taken += 1;
+                    commands
+                        .spawn(Screenshot::primary_window())
+                        .observe(save_to_disk(photo_path(*taken)))
+                        .observe(show_photo_panel);
+                }
+                PhotoButton::Back => next_pause_state.set(PauseState::Playing),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn photo_path(taken: u32) -> std::path::PathBuf {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let dir = std::path::PathBuf::from("photos");
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        bevy::log::warn!("Failed to create {}: {}", dir.display(), e);
+    }
+    dir.join(format!("limbo_pass_{seconds}_{taken}.png"))
+}
+
+/// The name of the downloaded file on the web.
+#[cfg(target_arch = "wasm32")]
+fn photo_path(taken: u32) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("limbo_pass_{taken}.png"))
+}
+
+fn show_photo_panel(_captured: On<ScreenshotCaptured>, mut panels: Query<&mut Visibility, With<PhotoPanel>>) {
+    for mut visibility in panels.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub fn refresh_photo_texts(photo: Res<PhotoSettings>, mut text_query: Query<(&mut Text, &PhotoText)>) {
+    if !photo.is_changed() {
+        return;
+    }
+    for (mut text, photo_text) in text_query.iter_mut() {
+        text.0 = photo_text.0.value(&photo);
+    }
+}
+
+pub fn apply_photo_settings(
+    mut commands: Commands,
+    photo: Res<PhotoSettings>,
+    mut cameras: Query<(Entity, &mut Projection, &mut Tonemapping), With<LookTransform>>,
+) {
+    if !photo.is_changed() {
+        return;
+    }
+    for (entity, mut projection, mut tonemapping) in cameras.iter_mut() {
+        if let Projection::Perspective(perspective) = &mut *projection {
+            perspective.fov = photo.fov.to_radians();
+        }
+        *tonemapping = photo.tonemapping;
+        let mut camera = commands.entity(entity);
+        camera.insert(Exposure { ev100: photo.exposure });
+        if photo.depth_of_field {
+            camera.insert(DepthOfField {
+                // bokeh doesn't work on the WebGL2 build
+                mode: DepthOfFieldMode::Gaussian,
+                focal_distance: photo.focal_distance,
+                ..default()
+            });
+        } else {
+            camera.remove::<DepthOfField>();
+        }
+    }
+}
+
+/// Flies the camera with the movement actions: forward/backward and strafing move, lift rises,
+/// turning yaws. Hold the right mouse button and move the mouse, or use the right stick, to look.
+pub fn fly_camera(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    settings: Res<Settings>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    gamepads: Query<&Gamepad>,
+    mut cameras: Query<&mut LookTransform>,
+) {
+    let dt = time.delta_secs();
+    let stick = gamepads
+        .iter()
+        .map(Gamepad::right_stick)
+        .fold(Vec2::ZERO, |a, b| if b.length() > a.length() { b } else { a });
+    let mut look_delta = Vec2::new(stick.x, -stick.y) * LOOK_SPEED * dt;
+    if mouse_buttons.pressed(MouseButton::Right) {
+        look_delta += mouse_motion.delta * MOUSE_LOOK_SPEED;
+    }
+    look_delta.x += (actions.value(Action::TurnRight) - actions.value(Action::TurnLeft)) * LOOK_SPEED * dt;
+    look_delta *= settings.orbit_scale();
+
+    for mut look in cameras.iter_mut() {
+        let forward = (look.target - look.eye).normalize_or(Vec3::X);
+        let yawed = Quat::from_rotation_y(-look_delta.x) * forward;
+        let right = yawed.cross(Vec3::Y).normalize_or_zero();
+        let pitched = Quat::from_axis_angle(right, -look_delta.y) * yawed;
+        // no looking straight up or down, the view would flip over
+        let forward = if pitched.y.abs() < 0.98 { pitched } else { yawed };
+
+        let movement = forward * (actions.value(Action::Forward) - actions.value(Action::Backward))
+            + right * (actions.value(Action::StrafeRight) - actions.value(Action::StrafeLeft))
+            + Vec3::Y * actions.value(Action::Lift);
+        look.eye += movement * FLY_SPEED * dt;
+        look.target = look.eye + forward;
+    }
+}
+
+pub fn cleanup_photo_panel(mut commands: Commands, query: Query<Entity, With<PhotoPanel>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
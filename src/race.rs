@@ -0,0 +1,320 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{
+    gltf::{Gltf, GltfNode},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::CheckpointReached;
+use crate::form::Form;
+use crate::gltf_collider;
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, InGame, PauseState};
+use crate::storage;
+
+// Racing the pass: leaving the start volume on the far side starts the clock, entering the
+// finish volume stops it. Everything here steps on `FixedUpdate` so a run takes the same time at
+// any framerate. Splits at each checkpoint are compared to the personal best, which is saved
+// for each level.
+
+/// The start and finish volumes span the whole pass across, so every route crosses them.
+const VOLUME_HALF_LENGTH: f32 = 0.5;
+const VOLUME_HALF_HEIGHT: f32 = 20.0;
+
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RaceCourse>()
+            .init_resource::<RaceTimer>()
+            .add_message::<RunFinished>()
+            .init_resource::<BestTimes>()
+            .add_systems(OnEnter(AppState::Running), (
+                load_best_times,
+                spawn_race_volumes,
+                spawn_race_hud,
+            ))
+            .add_systems(FixedUpdate, (
+                tick_timer.after(PhysicsSet::Writeback).run_if(in_state(PauseState::Playing)),
+                detect_start_finish.after(tick_timer).run_if(in_state(PauseState::Playing)),
+                record_splits.after(tick_timer).run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(Update, (
+                update_race_hud.run_if(in_state(AppState::Running)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_timer,
+            ));
+    }
+}
+
+/// Start and finish lines, facing +x along the pass, for levels without their own: glTF nodes
+/// named `START` and `FINISH` come first, then the level's `start` and `finish`. Insert before
+/// adding the plugin to set them.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RaceCourse {
+    pub start: Option<Transform>,
+    pub finish: Option<Transform>,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RaceVolume {
+    Start,
+    Finish,
+}
+
+impl RaceVolume {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Finish => "finish",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct RaceText;
+
+/// Sent on the tick the Form enters the finish volume.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct RunFinished {
+    pub time: Duration,
+    pub personal_best: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct RaceTimer {
+    pub elapsed: Duration,
+    pub running: bool,
+    pub finished: bool,
+    /// Time at each checkpoint reached this run, by checkpoint index.
+    pub splits: BTreeMap<usize, Duration>,
+    /// The latest split and how far ahead (negative) or behind the best run it was, in seconds.
+    pub last_split: Option<(usize, Duration, Option<f64>)>,
+}
+
+/// The personal best run on the current level, saved as `times_<level id>.ron`.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct BestTimes {
+    pub total: Option<Duration>,
+    pub splits: BTreeMap<usize, Duration>,
+}
+
+impl BestTimes {
+    pub const STORAGE_KEY: &'static str = "times";
+
+    pub fn save(&self, current_level: &CurrentLevel) {
+        storage::save(&current_level.level.storage_key(Self::STORAGE_KEY), self);
+    }
+}
+
+/// `m:ss.mmm`
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!("{}:{:02}.{:03}", millis / 60_000, millis / 1000 % 60, millis % 1000)
+}
+
+fn format_delta(seconds: f64) -> String {
+    format!("{}{:.3}", if seconds < 0.0 { "-" } else { "+" }, seconds.abs())
+}
+
+pub fn load_best_times(mut best: ResMut<BestTimes>, current_level: Res<CurrentLevel>) {
+    *best = storage::load(&current_level.level.storage_key(BestTimes::STORAGE_KEY)).unwrap_or_default();
+}
+
+pub fn spawn_race_volumes(
+    mut commands: Commands,
+    gltf_assets: Res<Assets<Gltf>>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    current_level: Res<CurrentLevel>,
+    course: Res<RaceCourse>,
+) {
+    let node_transform = |name: &str| {
+        let scenes_gltf = gltf_assets.get(&current_level.gltf)?;
+        gltf_collider::named_node_transforms(scenes_gltf, &gltf_nodes, name).first().copied()
+    };
+
+    let level = &current_level.level;
+    for (volume, transform) in [
+        (
+            RaceVolume::Start,
+            node_transform("START")
+                .or(level.start.map(Transform::from_translation))
+                .or(course.start),
+        ),
+        (
+            RaceVolume::Finish,
+            node_transform("FINISH")
+                .or(level.finish.map(Transform::from_translation))
+                .or(course.finish),
+        ),
+    ] {
+        let Some(transform) = transform else {
+            bevy::log::warn!("{} has no {} line, the clock won't run", level.name, volume.label());
+            continue;
+        };
+        commands.spawn((
+            transform.mul_transform(Transform::from_xyz(0.0, VOLUME_HALF_HEIGHT, 0.0)),
+            Collider::cuboid(VOLUME_HALF_LENGTH, VOLUME_HALF_HEIGHT, level.bounds),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            volume,
+            InGame,
+        ));
+    }
+}
+
+pub fn spawn_race_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                top: Val::Px(10.),
+                ..default()
+            },
+            InGame,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font: font_handle,
+                    font_size: 24.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                TextLayout::new_with_justify(Justify::Center),
+                RaceText,
+            ));
+        });
+}
+
+pub fn tick_timer(time: Res<Time<Fixed>>, mut timer: ResMut<RaceTimer>) {
+    if timer.running {
+        timer.elapsed += time.delta();
+    }
+}
+
+pub fn detect_start_finish(
+    mut collisions: MessageReader<CollisionEvent>,
+    form_query: Query<(Entity, &Transform), With<Form>>,
+    volumes: Query<(&RaceVolume, &Transform)>,
+    mut timer: ResMut<RaceTimer>,
+    mut best: ResMut<BestTimes>,
+    current_level: Res<CurrentLevel>,
+    mut finished: MessageWriter<RunFinished>,
+) {
+    let Ok((form_entity, form_transform)) = form_query.single() else {
+        return;
+    };
+
+    for collision in collisions.read() {
+        let (started, a, b) = match collision {
+            CollisionEvent::Started(a, b, _) => (true, *a, *b),
+            CollisionEvent::Stopped(a, b, _) => (false, *a, *b),
+        };
+        let other = if a == form_entity {
+            b
+        } else if b == form_entity {
+            a
+        } else {
+            continue;
+        };
+        let Ok((volume, volume_transform)) = volumes.get(other) else {
+            continue;
+        };
+
+        match volume {
+            // crossing the start line again, say after respawning at the start, restarts the run
+            RaceVolume::Start if !started => {
+                let local = volume_transform.compute_affine().inverse().transform_point3(form_transform.translation);
+                if local.x > 0.0 {
+                    *timer = RaceTimer {
+                        running: true,
+                        ..default()
+                    };
+                    bevy::log::info!("Run started");
+                }
+            }
+            RaceVolume::Finish if started && timer.running => {
+                timer.running = false;
+                timer.finished = true;
+                bevy::log::info!("Run finished in {}", format_time(timer.elapsed));
+                let personal_best = best.total.is_none_or(|total| timer.elapsed < total);
+                finished.write(RunFinished {
+                    time: timer.elapsed,
+                    personal_best,
+                });
+                if personal_best {
+                    *best = BestTimes {
+                        total: Some(timer.elapsed),
+                        splits: timer.splits.clone(),
+                    };
+                    best.save(&current_level);
+                    bevy::log::info!("New personal best");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn record_splits(
+    mut reached: MessageReader<CheckpointReached>,
+    best: Res<BestTimes>,
+    mut timer: ResMut<RaceTimer>,
+) {
+    for CheckpointReached(index) in reached.read() {
+        if !timer.running || timer.splits.contains_key(index) {
+            continue;
+        }
+        let split = timer.elapsed;
+        let delta = best
+            .splits
+            .get(index)
+            .map(|best_split| split.as_secs_f64() - best_split.as_secs_f64());
+        timer.splits.insert(*index, split);
+        timer.last_split = Some((*index, split, delta));
+    }
+}
+
+pub fn update_race_hud(
+    timer: Res<RaceTimer>,
+    best: Res<BestTimes>,
+    mut text_query: Query<&mut Text, With<RaceText>>,
+) {
+    if !timer.is_changed() && !best.is_changed() {
+        return;
+    }
+
+    let mut race_text = if timer.running || timer.finished {
+        format_time(timer.elapsed)
+    } else {
+        "cross the start line".to_string()
+    };
+    if let Some((index, split, delta)) = timer.last_split {
+        race_text += &format!("\ncheckpoint {}  {}", index + 1, format_time(split));
+        if let Some(delta) = delta {
+            race_text += &format!("  {}", format_delta(delta));
+        }
+    }
+    if let Some(total) = best.total {
+        race_text += &format!("\nbest {}", format_time(total));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.0 = race_text.clone();
+    }
+}
+
+pub fn reset_timer(mut timer: ResMut<RaceTimer>) {
+    *timer = RaceTimer::default();
+}
@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::endless::{self, EndlessSeed};
+use crate::form::{self, FormPreset, Movement, Movements};
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, PauseState};
+use crate::storage;
+
+// Every run records the Form's `Movements` once per physics tick and saves them as `last_run`
+// when the run ends. Start the game with `LIMBO_PASS_REPLAY=path/to/run.ron` (or insert
+// `Replay::play(recording)` before adding the plugin) and each run feeds the recording back
+// instead of the player's input. Physics steps on a fixed timestep, so the same recording plays
+// out the same way every time.
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Replay>() {
+            app.insert_resource(replay_from_env().unwrap_or_default());
+        }
+        // the recording only makes sense with the tuning it was made with, and on the endless
+        // pass with the seed it was made on
+        if let Some(replay) = app.world().get_resource::<Replay>()
+            && replay.mode == ReplayMode::Play
+        {
+            let preset = FormPreset(replay.recording.preset.clone());
+            let seed = endless::seed_of(&replay.recording.level);
+            app.insert_resource(preset);
+            if let Some(seed) = seed {
+                app.insert_resource(EndlessSeed(seed));
+            }
+        }
+
+        app
+            .add_systems(OnEnter(AppState::Running), (
+                start_replay,
+            ))
+            .add_systems(FixedUpdate, (
+                feed_replay
+                    .after(form::get_movement)
+                    .before(form::apply_movement)
+                    .run_if(in_state(PauseState::Playing)),
+                record_movements
+                    .after(feed_replay)
+                    .before(form::apply_movement)
+                    .run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                save_recording,
+            ));
+    }
+}
+
+/// A run as the Form's movements on each physics tick, run-length encoded since input rarely
+/// changes from one tick to the next.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Recording {
+    /// The `FormPreset` the run was recorded with.
+    pub preset: String,
+    /// The id of the level the run was recorded on.
+    pub level: String,
+    /// How many ticks in a row had the same movements, and the movements.
+    pub ticks: Vec<(u32, Vec<Movement>)>,
+}
+
+impl Recording {
+    pub const STORAGE_KEY: &'static str = "last_run";
+
+    pub fn push(&mut self, movements: &[Movement]) {
+        match self.ticks.last_mut() {
+            Some((count, last)) if last.as_slice() == movements => *count += 1,
+            _ => self.ticks.push((1, movements.to_vec())),
+        }
+    }
+
+    /// Total number of ticks recorded.
+    pub fn len(&self) -> u32 {
+        self.ticks.iter().map(|(count, _)| count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayMode {
+    #[default]
+    Record,
+    Play,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    pub recording: Recording,
+    /// Position in `recording.ticks` while playing, entry and ticks into it.
+    cursor: (usize, u32),
+}
+
+impl Replay {
+    pub fn play(recording: Recording) -> Self {
+        Self {
+            mode: ReplayMode::Play,
+            recording,
+            cursor: (0, 0),
+        }
+    }
+
+    /// The movements for the next tick, `None` once the recording is over.
+    fn next(&mut self) -> Option<&[Movement]> {
+        let (entry, tick) = self.cursor;
+        let (count, _) = self.recording.ticks.get(entry)?;
+        self.cursor = if tick + 1 < *count { (entry, tick + 1) } else { (entry + 1, 0) };
+        Some(self.recording.ticks[entry].1.as_slice())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_from_env() -> Option<Replay> {
+    let path = std::env::var("LIMBO_PASS_REPLAY").ok()?;
+    let recording = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| ron::de::from_str::<Recording>(&text).map_err(|e| e.to_string()));
+    match recording {
+        Ok(recording) => {
+            bevy::log::info!("Replaying {} ({} ticks)", path, recording.len());
+            Some(Replay::play(recording))
+        }
+        Err(e) => {
+            bevy::log::warn!("Could not load replay {}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn replay_from_env() -> Option<Replay> {
+    None
+}
+
+pub fn start_replay(mut replay: ResMut<Replay>, preset: Res<FormPreset>, current_level: Res<CurrentLevel>) {
+    match replay.mode {
+        ReplayMode::Record => {
+            replay.recording = Recording {
+                preset: preset.0.clone(),
+                level: current_level.level.id.clone(),
+                ..default()
+            };
+        }
+        ReplayMode::Play => {
+            if replay.recording.level != current_level.level.id {
+                bevy::log::warn!(
+                    "Replaying a run recorded on {} on {}, it won't play out the same",
+                    replay.recording.level,
+                    current_level.level.id
+                );
+            }
+            replay.cursor = (0, 0);
+        }
+    }
+}
+
+/// Swaps the player's movements for the recorded ones.
+pub fn feed_replay(mut replay: ResMut<Replay>, mut form_query: Query<&mut Movements>) {
+    if replay.mode != ReplayMode::Play {
+        return;
+    }
+    let Ok(mut movements) = form_query.single_mut() else {
+        return;
+    };
+    movements.0.clear();
+    if let Some(recorded) = replay.next() {
+        movements.0.extend_from_slice(recorded);
+    }
+}
+
+pub fn record_movements(mut replay: ResMut<Replay>, form_query: Query<&Movements>) {
+    if replay.mode != ReplayMode::Record {
+        return;
+    }
+    if let Ok(movements) = form_query.single() {
+        replay.recording.push(&movements.0);
+    }
+}
+
+pub fn save_recording(replay: Res<Replay>) {
+    if replay.mode == ReplayMode::Record && !replay.recording.is_empty() {
+        storage::save(Recording::STORAGE_KEY, &replay.recording);
+        bevy::log::info!("Saved run of {} ticks", replay.recording.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_round_trips_run_length_encoded() {
+        let forward = [Movement::PushForward(1.0)];
+        let turning = [Movement::PushForward(0.5), Movement::TurnLeft(0.25)];
+        let ticks: [&[Movement]; 7] = [&forward, &forward, &forward, &turning, &[], &[], &forward];
+        let mut recording = Recording {
+            preset: "floaty".to_string(),
+            level: "limbo_pass".to_string(),
+            ..default()
+        };
+        for movements in ticks {
+            recording.push(movements);
+        }
+        assert_eq!(recording.ticks.len(), 4);
+        assert_eq!(recording.len(), 7);
+
+        let text = ron::ser::to_string(&recording).unwrap();
+        let loaded: Recording = ron::de::from_str(&text).unwrap();
+        assert_eq!(loaded.preset, recording.preset);
+        assert_eq!(loaded.level, recording.level);
+        assert_eq!(loaded.ticks, recording.ticks);
+
+        // and plays back tick by tick
+        let mut replay = Replay::play(loaded);
+        let mut played = Vec::new();
+        while let Some(movements) = replay.next() {
+            played.push(movements.to_vec());
+        }
+        assert_eq!(played, ticks.map(<[Movement]>::to_vec));
+    }
+}
@@ -1,174 +1,106 @@
-use bevy::{
-    gltf::{Gltf, GltfMesh},
-    prelude::*,
-};
-use bevy::mesh::{Indices, VertexAttributeValues};
-
-fn hex_to_color(hex: &str) -> Color {
-    let hex = hex.trim_start_matches('#');
-    let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-    let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-    let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-    Color::srgb_u8(r, g, b)
-}
+use bevy::{gltf::Gltf, prelude::*};
 use bevy_rapier3d::prelude::*;
 
-use crate::form::{Form, Movements};
+use crate::camera;
+use crate::form::{Form, FormConfig, FormConfigHandle, Movements};
+use crate::gltf_collider::GltfColliders;
+use crate::level_nodes::{self, LevelLayout};
+use crate::levels::CurrentLevel;
+use crate::lights;
+use crate::loading::LoadingAssets;
+use crate::setup::{AppState, InGame};
+use crate::terrain::{self, TerrainCollider};
 
+pub struct ScenesPlugin;
+
+impl Plugin for ScenesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TerrainCollider>()
+            .add_systems(OnEnter(AppState::Loading), load)
+            .add_systems(OnEnter(AppState::Running), spawn.after(level_nodes::read_level_layout));
+    }
+}
+
+/// The glTF with the `FORM` scene, the levels come from `levels`.
 #[derive(Resource)]
 pub struct SceneHandle {
     pub handle: Handle<Gltf>,
 }
 
-pub fn load(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(SceneHandle {
-        handle: asset_server.load("gltf/limbo_pass.gltf"),
-    });
+pub fn load(mut commands: Commands, asset_server: Res<AssetServer>, mut loading_assets: ResMut<LoadingAssets>) {
+    let handle = asset_server.load("gltf/limbo_pass.gltf");
+    loading_assets.track(&handle, "the Form");
+    commands.insert_resource(SceneHandle { handle });
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn(
     gltf_assets: Res<Assets<Gltf>>,
-    gltf_meshes: Res<Assets<GltfMesh>>,
-    meshes: Res<Assets<Mesh>>,
+    gltf_colliders: GltfColliders,
     scene_handle: Res<SceneHandle>,
+    current_level: Res<CurrentLevel>,
+    layout: Res<LevelLayout>,
+    form_configs: Res<Assets<FormConfig>>,
+    form_config_handle: Res<FormConfigHandle>,
+    terrain_collider: Res<TerrainCollider>,
     mut commands: Commands,
 ) {
-    let point_light_color_hex_string = "70FF00";
+    let level = &current_level.level;
     if let Some(scenes_gltf) = gltf_assets.get(&scene_handle.handle) {
-        // Spawn foot light (this was previously unused)
-        commands.spawn((
-            PointLight {
-                color: hex_to_color(point_light_color_hex_string),
-                range: 2.,
-                intensity: 100.,
-                ..Default::default()
-            },
-            Transform::default(),
-        ));
+        let form_config = form_configs
+            .get(&form_config_handle.handle)
+            .cloned()
+            .unwrap_or_default();
 
         let scene_handle = scenes_gltf.named_scenes["FORM"].clone();
         let form_entity = commands
             .spawn((
                 SceneRoot(scene_handle),
-                Transform::from_xyz(-45.0, 1.5, 0.0),
+                layout.spawn,
                 RigidBody::Dynamic,
-                Collider::ball(2.3),
+                Collider::ball(form_config.collider_radius),
                 LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z,
                 Velocity::zero(),
                 ExternalForce::default(),
                 Movements::default(),
-                GravityScale(3.0), // Increased from 2.0 for faster falling 
-                Form {
-                    // nothing special about these values, just played around until it felt like a ghost
-                    // Reduced Y drag so falling is faster, but lift thrust (90.0 * 100.0 = 9000) still works
-                    // Reduced X/Z thrust to slow horizontal movement
-                    thrust: Vec3::new(200.0, 100.0, 200.0), // Reduced from 300.0 to 200.0 for X/Z
-                    drag: Vec3::new(250.0, 300.0, 250.0), // Reduced Y drag from 500.0 to 300.0
-                },
+                GravityScale(form_config.gravity_scale),
+                Form::from_config(&form_config),
+                InGame,
             ))
+            .with_child(lights::foot_light())
+            .observe(camera::put_on_form_layer)
             .id();
         
         bevy::log::info!("Form entity spawned with collider: {:?}", form_entity);
+    }
 
-        // Spawn terrain scene - it will spawn even if we can't extract mesh data for collider
-        let terrain_scene_handle = scenes_gltf.named_scenes["TERRAIN"].clone();
-        let mut terrain_entity = commands.spawn(SceneRoot(terrain_scene_handle));
-        
-        // Terrain needs to be a static rigid body for collisions to work
-        terrain_entity.insert(RigidBody::Fixed);
-
-        // Try to add collider if we can extract mesh data
-        let terrain_mesh_handle = scenes_gltf.named_meshes.get("TERRAIN");
-        
-        if let Some(terrain_mesh_handle) = terrain_mesh_handle {
-            bevy::log::info!("Found TERRAIN mesh handle: {:?}", terrain_mesh_handle);
-            
-            let terrain_mesh: Option<&Mesh> = gltf_meshes
-                .get(terrain_mesh_handle)
-                .and_then(|gltf_mesh| {
-                    bevy::log::info!("GltfMesh has {} primitives", gltf_mesh.primitives.len());
-                    gltf_mesh.primitives.first()
-                })
-                .and_then(|terrain_primitive| {
-                    bevy::log::info!("Trying to get mesh from primitive: {:?}", terrain_primitive.mesh);
-                    meshes.get(&terrain_primitive.mesh)
-                });
-
-            if let Some(terrain_mesh) = terrain_mesh {
-                bevy::log::info!("Successfully got terrain mesh");
-                let attribute_positions = terrain_mesh.attribute(Mesh::ATTRIBUTE_POSITION);
-                let indices = terrain_mesh.indices();
-
-                // Match on both attribute and index types
-                match (attribute_positions, indices) {
-                    (
-                        Some(VertexAttributeValues::Float32x3(vertex_values)),
-                        Some(Indices::U32(index_values)),
-                    ) => {
-                        let vertices: Vec<Vect> = vertex_values
-                            .iter()
-                            .map(|v| Vec3::new(v[0], v[1], v[2]))
-                            .collect();
-
-                        let indices: Vec<[u32; 3]> = index_values
-                            .chunks(3)
-                            .map(|chunk| [chunk[0], chunk[1], chunk[2]])
-                            .collect();
+    // the endless pass makes its own terrain, see `endless`
+    if level.seed.is_some() {
+        return;
+    }
+    let Some(level_gltf) = gltf_assets.get(&current_level.gltf) else {
+        bevy::log::warn!("Level {} isn't loaded", level.name);
+        return;
+    };
+    let Some(terrain_scene_handle) = level_gltf.named_scenes.get(level.terrain_scene.as_str()) else {
+        bevy::log::warn!("No scene named {} in {}", level.terrain_scene, level.gltf);
+        return;
+    };
 
-                        let vertex_count = vertices.len();
-                        let triangle_count = indices.len();
-                        if let Ok(collider) = Collider::trimesh(vertices, indices) {
-                            terrain_entity.insert(collider);
-                            terrain_entity.insert(ActiveEvents::COLLISION_EVENTS);
-                            bevy::log::info!("Terrain collider added successfully with {} vertices, {} triangles", vertex_count, triangle_count);
-                        } else {
-                            bevy::log::warn!("Failed to create terrain trimesh collider");
-                        }
-                    }
-                    (
-                        Some(VertexAttributeValues::Float32x3(vertex_values)),
-                        Some(Indices::U16(index_values)),
-                    ) => {
-                        // Handle U16 indices
-                        let vertices: Vec<Vect> = vertex_values
-                            .iter()
-                            .map(|v| Vec3::new(v[0], v[1], v[2]))
-                            .collect();
+    // Spawn terrain scene - it will spawn even if we can't extract mesh data for collider
+    let mut terrain_entity = commands.spawn((SceneRoot(terrain_scene_handle.clone()), InGame));
+    terrain_entity.observe(lights::use_gltf_lights);
 
-                        let indices: Vec<[u32; 3]> = index_values
-                            .chunks(3)
-                            .map(|chunk| [chunk[0] as u32, chunk[1] as u32, chunk[2] as u32])
-                            .collect();
+    // Terrain needs to be a static rigid body for collisions to work
+    terrain_entity.insert(RigidBody::Fixed);
 
-                        let vertex_count = vertices.len();
-                        let triangle_count = indices.len();
-                        if let Ok(collider) = Collider::trimesh(vertices, indices) {
-                            terrain_entity.insert(collider);
-                            terrain_entity.insert(ActiveEvents::COLLISION_EVENTS);
-                            bevy::log::info!("Terrain collider added successfully with {} vertices, {} triangles (U16 indices)", vertex_count, triangle_count);
-                        } else {
-                            bevy::log::warn!("Failed to create terrain trimesh collider");
-                        }
-                    }
-                    (pos_attr, idx) => {
-                        bevy::log::warn!("Failed to extract terrain mesh data for collider - unsupported format");
-                        if pos_attr.is_none() {
-                            bevy::log::warn!("  - No position attribute found");
-                        } else {
-                            bevy::log::warn!("  - Position attribute is not Float32x3");
-                        }
-                        if idx.is_none() {
-                            bevy::log::warn!("  - No indices found");
-                        } else {
-                            bevy::log::warn!("  - Indices are not U32 or U16");
-                        }
-                    }
-                }
-            } else {
-                bevy::log::warn!("Failed to get terrain mesh from GltfMesh or Mesh assets");
-            }
-        } else {
-            bevy::log::warn!("TERRAIN mesh not found in named_meshes");
+    // Try to add collider if we can extract mesh data
+    let terrain_geometry = gltf_colliders.geometry(level_gltf, &level.terrain_mesh);
+    match terrain_geometry.and_then(|geometry| terrain::terrain_collider(&geometry, *terrain_collider)) {
+        Some(collider) => {
+            terrain_entity.insert((collider, ActiveEvents::COLLISION_EVENTS));
         }
+        None => bevy::log::warn!("No collider for the {} mesh", level.terrain_mesh),
     }
 }
@@ -0,0 +1,198 @@
+use bevy::{
+    light::{DirectionalLightShadowMap, PointLightShadowMap},
+    prelude::*,
+    window::{PresentMode, PrimaryWindow},
+};
+use bevy_kira_audio::{prelude::Decibels, Audio, AudioControl};
+use serde::{Deserialize, Serialize};
+use smooth_bevy_cameras::controllers::orbit::OrbitCameraController;
+
+use crate::storage;
+
+// Player options that survive restarts. Loaded once when the plugin is built, applied whenever
+// they change (and to cameras / lights as they're spawned), saved when the settings screen closes.
+// There's no sfx volume yet, the game has no sound effects for one to turn down.
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(storage::load::<Settings>(Settings::STORAGE_KEY).unwrap_or_default())
+            .add_systems(Update, (
+                apply_settings,
+            ));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadowQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl ShadowQuality {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// Only used while shadows are on, `Off` turns them off on every `ShadowCaster`.
+    pub fn map_size(&self) -> usize {
+        match self {
+            Self::Off | Self::Low => 512,
+            Self::Medium => 1024,
+            Self::High => 2048,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Off => Self::Low,
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::Off,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            Self::Off => Self::High,
+            Self::Low => Self::Off,
+            Self::Medium => Self::Low,
+            Self::High => Self::Medium,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VsyncSetting {
+    On,
+    Off,
+}
+
+impl VsyncSetting {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::On => "on",
+            Self::Off => "off",
+        }
+    }
+
+    pub fn toggled(&self) -> Self {
+        match self {
+            Self::On => Self::Off,
+            Self::Off => Self::On,
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        match self {
+            Self::On => PresentMode::AutoVsync,
+            Self::Off => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// 0.0 (silent) to 1.0 (full volume)
+    pub music_volume: f32,
+    pub shadows: ShadowQuality,
+    pub vsync: VsyncSetting,
+    /// Multiplies the orbit camera's default rotate speed, for mouse, stick and touch alike
+    pub mouse_sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            shadows: ShadowQuality::High,
+            vsync: VsyncSetting::On,
+            mouse_sensitivity: 1.0,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl Settings {
+    pub const STORAGE_KEY: &'static str = "settings";
+
+    /// Scale for orbiting the camera, negative on inverted axes.
+    pub fn orbit_scale(&self) -> Vec2 {
+        Vec2::new(
+            if self.invert_x { -1.0 } else { 1.0 },
+            if self.invert_y { -1.0 } else { 1.0 },
+        ) * self.mouse_sensitivity
+    }
+
+    pub fn save(&self) {
+        storage::save(Self::STORAGE_KEY, self);
+    }
+}
+
+/// Marks lights whose shadows follow the shadow quality setting.
+#[derive(Component)]
+pub struct ShadowCaster;
+
+fn volume_to_decibels(volume: f32) -> Decibels {
+    if volume <= 0.0 {
+        Decibels::SILENCE
+    } else {
+        Decibels(20.0 * volume.log10())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_settings(
+    settings: Res<Settings>,
+    new_cameras: Query<(), Added<OrbitCameraController>>,
+    new_lights: Query<(), Added<ShadowCaster>>,
+    mut shadow_map: ResMut<PointLightShadowMap>,
+    mut directional_shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut point_lights: Query<&mut PointLight, With<ShadowCaster>>,
+    mut spot_lights: Query<&mut SpotLight, With<ShadowCaster>>,
+    mut directional_lights: Query<&mut DirectionalLight, With<ShadowCaster>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut cameras: Query<&mut OrbitCameraController>,
+    music: Res<Audio>,
+) {
+    if !settings.is_changed() && new_cameras.is_empty() && new_lights.is_empty() {
+        return;
+    }
+
+    music.set_volume(volume_to_decibels(settings.music_volume));
+
+    let shadows_enabled = settings.shadows != ShadowQuality::Off;
+    shadow_map.size = settings.shadows.map_size();
+    directional_shadow_map.size = settings.shadows.map_size();
+    for mut light in point_lights.iter_mut() {
+        light.shadows_enabled = shadows_enabled;
+    }
+    for mut light in spot_lights.iter_mut() {
+        light.shadows_enabled = shadows_enabled;
+    }
+    for mut light in directional_lights.iter_mut() {
+        light.shadows_enabled = shadows_enabled;
+    }
+
+    for mut window in windows.iter_mut() {
+        window.present_mode = settings.vsync.present_mode();
+    }
+
+    let default_sensitivity = OrbitCameraController::default().mouse_rotate_sensitivity;
+    for mut controller in cameras.iter_mut() {
+        controller.mouse_rotate_sensitivity = default_sensitivity * settings.orbit_scale();
+    }
+}
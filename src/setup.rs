@@ -1,30 +1,345 @@
-use crate::scenes::SceneHandle;
+use crate::input::{Action, Binding, Bindings};
+use crate::loading::LoadingAssets;
+use crate::settings::Settings;
 use crate::theme::ThemeState;
-use bevy::asset::LoadState;
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::input_focus::{
+    tab_navigation::{NavAction, TabGroup, TabIndex, TabNavigation},
+    InputFocus,
+};
 use bevy::prelude::*;
 use bevy_kira_audio::AudioControl;
+use bevy_rapier3d::prelude::RapierConfiguration;
 use smooth_bevy_cameras::controllers::orbit::{OrbitCameraBundle, OrbitCameraController};
 
+pub struct SetupPlugin;
+
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<AppState>()
+            .init_resource::<InputFocus>()
+            .add_systems(PreUpdate, (
+                gamepad_focus_navigation.after(bevy::ui::UiSystems::Focus),
+            ))
+            .add_systems(Update, (
+                highlight_buttons,
+            ))
+            // once, not on entering `Loading`, which happens again when retrying failed assets
+            .add_systems(Startup, (
+                camera,
+                lighting,
+                physics,
+            ))
+            .init_resource::<LoadFailures>()
+            .add_systems(OnEnter(AppState::Loading), (
+                spawn_loading_screen,
+            ))
+            .add_systems(Update, (
+                update_loading_screen.run_if(in_state(AppState::Loading)),
+                check_loaded.run_if(in_state(AppState::Loading)),
+            ))
+            .add_systems(OnExit(AppState::Loading), (
+                cleanup_loading_screen,
+            ))
+            .add_systems(OnEnter(AppState::LoadFailed), (
+                spawn_load_error_screen,
+            ))
+            .add_systems(Update, (
+                handle_load_error_buttons.run_if(in_state(AppState::LoadFailed)),
+            ))
+            .add_systems(OnExit(AppState::LoadFailed), (
+                cleanup_load_error_screen,
+            ))
+            .add_systems(OnEnter(AppState::Menu), (
+                spawn_menu,
+            ))
+            .add_systems(Update, (
+                handle_menu_buttons.run_if(in_state(AppState::Menu)),
+            ))
+            .add_systems(OnExit(AppState::Menu), (
+                cleanup_menu,
+            ))
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(AppState::Controls), (
+                spawn_controls_screen,
+            ))
+            .add_systems(OnEnter(PauseState::Controls), (
+                spawn_controls_screen,
+            ))
+            .add_systems(Update, (
+                handle_controls_buttons.run_if(screen_open(AppState::Controls, PauseState::Controls)),
+                capture_binding.after(handle_controls_buttons).run_if(screen_open(AppState::Controls, PauseState::Controls)),
+                refresh_binding_texts.after(capture_binding).run_if(screen_open(AppState::Controls, PauseState::Controls)),
+            ))
+            .add_systems(OnExit(AppState::Controls), (
+                cleanup_controls_screen,
+            ))
+            .add_systems(OnExit(PauseState::Controls), (
+                cleanup_controls_screen,
+            ))
+            .add_systems(OnEnter(AppState::Settings), (
+                spawn_settings_screen,
+            ))
+            .add_systems(OnEnter(PauseState::Settings), (
+                spawn_settings_screen,
+            ))
+            .add_systems(Update, (
+                handle_settings_buttons.run_if(screen_open(AppState::Settings, PauseState::Settings)),
+                refresh_setting_texts.after(handle_settings_buttons).run_if(screen_open(AppState::Settings, PauseState::Settings)),
+            ))
+            .add_systems(OnExit(AppState::Settings), (
+                cleanup_settings_screen,
+            ))
+            .add_systems(OnExit(PauseState::Settings), (
+                cleanup_settings_screen,
+            ))
+            .add_sub_state::<PauseState>()
+            .add_systems(OnEnter(AppState::Running), (
+                spawn_controls_text,
+            ))
+            .add_systems(Update, (
+                handle_music_toggle.run_if(in_state(AppState::Running)),
+                toggle_pause.run_if(in_state(PauseState::Playing).or(in_state(PauseState::Paused))),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                cleanup_game,
+            ))
+            .add_systems(OnEnter(PauseState::Playing), (
+                resume_physics,
+            ))
+            .add_systems(OnExit(PauseState::Playing), (
+                pause_physics,
+            ))
+            .add_systems(OnEnter(PauseState::Paused), (
+                spawn_pause_menu,
+            ))
+            .add_systems(Update, (
+                handle_pause_buttons.run_if(in_state(PauseState::Paused)),
+            ))
+            .add_systems(OnExit(PauseState::Paused), (
+                cleanup_pause_menu,
+            ))
+            .add_systems(OnEnter(AppState::Restarting), (
+                restart,
+            ));
+    }
+}
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+/// The filled part of the loading progress bar.
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingStatusText;
+
+#[derive(Component)]
+pub struct LoadErrorScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum LoadErrorButton {
+    Retry,
+    ContinueWithoutMusic,
+}
+
+/// An asset that failed to load, shown on the load error screen.
+#[derive(Debug, Clone)]
+pub struct LoadFailure {
+    pub path: String,
+    pub reason: String,
+    /// Tracked as optional, the game can do without it.
+    pub optional: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct LoadFailures(pub Vec<LoadFailure>);
+
+impl LoadFailures {
+    pub fn only_optional(&self) -> bool {
+        self.0.iter().all(|failure| failure.optional)
+    }
+}
+
+#[derive(Component)]
+pub struct MenuScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum MenuButton {
+    Play,
+    Settings,
+}
+
+#[derive(Component)]
+pub struct ControlsScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum ControlsButton {
+    Rebind(Action),
+    Reset,
+    Back,
+}
+
 #[derive(Component)]
-pub(crate) struct LoadingScreen;
+pub struct BindingText(pub Action);
 
 #[derive(Component)]
-pub(crate) struct MenuScreen;
+pub struct SettingsScreen;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    MusicVolume,
+    Shadows,
+    Vsync,
+    MouseSensitivity,
+    InvertX,
+    InvertY,
+}
+
+impl SettingKind {
+    const ALL: [SettingKind; 6] = [
+        SettingKind::MusicVolume,
+        SettingKind::Shadows,
+        SettingKind::Vsync,
+        SettingKind::MouseSensitivity,
+        SettingKind::InvertX,
+        SettingKind::InvertY,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::MusicVolume => "music volume",
+            Self::Shadows => "shadows",
+            Self::Vsync => "vsync",
+            Self::MouseSensitivity => "look sensitivity",
+            Self::InvertX => "invert look x",
+            Self::InvertY => "invert look y",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        match self {
+            Self::MusicVolume => format!("{:.0}%", settings.music_volume * 100.0),
+            Self::Shadows => settings.shadows.label().to_string(),
+            Self::Vsync => settings.vsync.label().to_string(),
+            Self::MouseSensitivity => format!("{:.1}x", settings.mouse_sensitivity),
+            Self::InvertX => on_off(settings.invert_x),
+            Self::InvertY => on_off(settings.invert_y),
+        }
+    }
+
+    /// Steps the setting up (`step` 1) or down (`step` -1), toggles just flip.
+    fn adjust(&self, settings: &mut Settings, step: f32) {
+        match self {
+            Self::MusicVolume => settings.music_volume = (settings.music_volume + 0.1 * step).clamp(0.0, 1.0),
+            Self::Shadows => {
+                settings.shadows = if step > 0.0 { settings.shadows.next() } else { settings.shadows.previous() }
+            }
+            Self::Vsync => settings.vsync = settings.vsync.toggled(),
+            Self::MouseSensitivity => {
+                settings.mouse_sensitivity = (settings.mouse_sensitivity + 0.1 * step).clamp(0.1, 3.0)
+            }
+            Self::InvertX => settings.invert_x = !settings.invert_x,
+            Self::InvertY => settings.invert_y = !settings.invert_y,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum SettingsButton {
+    Decrease(SettingKind),
+    Increase(SettingKind),
+    Controls,
+    Back,
+}
 
 #[derive(Component)]
-pub(crate) struct MusicToggleButton;
+pub struct SettingText(pub SettingKind);
 
+/// The action being rebound on the controls screen, if any.
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    pub action: Option<Action>,
+    /// Whether a key replaced the action's keys yet, further keys are added alongside it.
+    pub captured_key: bool,
+    /// Whether a gamepad button replaced the action's buttons yet.
+    pub captured_button: bool,
+}
+
+impl Rebinding {
+    pub fn captured(&self) -> bool {
+        self.captured_key || self.captured_button
+    }
+}
+
+/// Everything spawned for a run (the Form, terrain, HUD), despawned when leaving `Running`.
 #[derive(Component)]
-pub(crate) struct MusicToggleText;
+pub struct InGame;
+
+#[derive(Component)]
+pub struct PauseScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum PauseButton {
+    Resume,
+    Settings,
+    Restart,
+    ChangeLevel,
+    Quit,
+}
+
+#[derive(Component)]
+pub struct MusicToggleButton;
+
+#[derive(Component)]
+pub struct MusicToggleText;
 
 type ButtonInteractionQuery<'w, 's> = Query<'w, 's, (&'static Interaction, &'static Children), (Changed<Interaction>, With<Button>)>;
+pub(crate) type ButtonActionQuery<'w, 's, T> = Query<'w, 's, (&'static Interaction, &'static T), (Changed<Interaction>, With<Button>)>;
 
 #[derive(States, Debug, Clone, Eq, PartialEq, Hash, Default)]
 pub enum AppState {
     #[default]
     Loading,
+    /// Something failed to load, the error screen offers a retry.
+    LoadFailed,
     Menu,
+    /// Picking a level to play, see `levels`.
+    LevelSelect,
+    Settings,
+    Controls,
     Running,
+    /// Passes straight back to `Running`, so the world is torn down and spawned fresh.
+    Restarting,
+}
+
+/// Whether a run is being played or is paused, only exists while in `AppState::Running`.
+#[derive(SubStates, Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[source(AppState = AppState::Running)]
+pub enum PauseState {
+    #[default]
+    Playing,
+    Paused,
+    Settings,
+    Controls,
+    /// The ghost clipped a limbo bar, the run is over.
+    Failed,
+    /// Frozen for photo mode, see `photo`.
+    Photo,
+}
+
+/// Run condition for screens that open both from the main menu and the pause menu.
+fn screen_open(
+    app_screen: AppState,
+    pause_screen: PauseState,
+) -> impl FnMut(Res<State<AppState>>, Option<Res<State<PauseState>>>) -> bool {
+    move |app_state, pause_state| {
+        *app_state.get() == app_screen
+            || pause_state.is_some_and(|pause_state| *pause_state.get() == pause_screen)
+    }
 }
 
 fn hex_to_color(hex: &str) -> Color {
@@ -43,26 +358,7 @@ pub fn lighting(mut commands: Commands, mut ambient_light: ResMut<AmbientLight>)
     // Increase ambient light brightness for better global illumination
     ambient_light.brightness = 600.0; // Increased from 0.6 for more visibility
     ambient_light.color = Color::srgb(0.75, 0.75, 0.75); // SILVER equivalent
-    
-    let point_light_color_hex_string = "AB69E7";
-    let color = hex_to_color(point_light_color_hex_string);
-    
-    // Spawn 4 point lights around the scene
-    // Making them WAY bigger and brighter to test visibility
-    for (x, z) in [(-40.0, 0.0), (40.0, 0.0), (0.0, -40.0), (0.0, 40.0)] {
-        commands.spawn((
-            PointLight {
-                color,
-                range: 500.0, 
-                intensity: 10_000_000.0,
-                shadows_enabled: true,
-                ..default()
-            },
-            Transform::from_xyz(x, 15.0, z),
-        ));
-    }
-    
-    bevy::log::info!("Spawned 4 point lights with default settings");
+    // the point lights come with the level, see `lights`
 }
 
 // Gravity scale will be applied to the form entity in scenes.rs
@@ -71,6 +367,18 @@ pub fn physics() {
     // Gravity scale is set on the form entity in scenes::spawn
 }
 
+pub fn pause_physics(mut rapier_configs: Query<&mut RapierConfiguration>) {
+    for mut config in rapier_configs.iter_mut() {
+        config.physics_pipeline_active = false;
+    }
+}
+
+pub fn resume_physics(mut rapier_configs: Query<&mut RapierConfiguration>) {
+    for mut config in rapier_configs.iter_mut() {
+        config.physics_pipeline_active = true;
+    }
+}
+
 pub fn camera(mut commands: Commands) {
     commands
         .spawn(Camera3d::default())
@@ -84,15 +392,30 @@ pub fn camera(mut commands: Commands) {
 
 pub fn check_loaded(
     asset_server: Res<AssetServer>,
-    audio_state: Res<ThemeState>,
-    scene_handle: Res<SceneHandle>,
+    loading_assets: Res<LoadingAssets>,
+    mut load_failures: ResMut<LoadFailures>,
     mut state: ResMut<NextState<AppState>>,
 ) {
-    if !matches!(asset_server.get_load_state(&audio_state.loop_handle), Some(LoadState::Loaded)) {
-        return;
+    let mut failures = Vec::new();
+    for tracked in loading_assets.iter() {
+        match tracked.state(&asset_server) {
+            RecursiveDependencyLoadState::Loaded => {}
+            RecursiveDependencyLoadState::Failed(error) => failures.push(LoadFailure {
+                path: tracked.path(),
+                reason: error.to_string(),
+                optional: tracked.optional,
+            }),
+            // wait for everything to settle so the error screen lists every failure
+            _ => return,
+        }
     }
 
-    if !matches!(asset_server.get_load_state(&scene_handle.handle), Some(LoadState::Loaded)) {
+    if !failures.is_empty() {
+        for failure in &failures {
+            bevy::log::warn!("Failed to load {}: {}", failure.path, failure.reason);
+        }
+        load_failures.0 = failures;
+        state.set(AppState::LoadFailed);
         return;
     }
 
@@ -100,10 +423,15 @@ pub fn check_loaded(
     state.set(AppState::Menu)
 }
 
-pub fn spawn_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn spawn_loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
     // Use the same monospace font as the menu for consistency
     let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
-    
+    loading_assets.track(&font_handle, "font");
+
     commands
         .spawn((
             Node {
@@ -112,6 +440,7 @@ pub fn spawn_loading_screen(mut commands: Commands, asset_server: Res<AssetServe
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(24.),
                 ..default()
             },
             LoadingScreen,
@@ -119,16 +448,190 @@ pub fn spawn_loading_screen(mut commands: Commands, asset_server: Res<AssetServe
         .with_children(|parent| {
             parent.spawn((
                 Text("limbo pass is loading...".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(400.),
+                        height: Val::Px(8.),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.9, 0.9, 0.9, 0.1)),
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Node {
+                            width: Val::Percent(0.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.9, 0.9, 0.9)),
+                        LoadingBar,
+                    ));
+                });
+
+            parent.spawn((
+                Text::default(),
                 TextFont {
                     font: font_handle,
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                LoadingStatusText,
+            ));
+        });
+}
+
+pub fn update_loading_screen(
+    asset_server: Res<AssetServer>,
+    loading_assets: Res<LoadingAssets>,
+    mut bar_query: Query<&mut Node, With<LoadingBar>>,
+    mut status_query: Query<&mut Text, With<LoadingStatusText>>,
+) {
+    for mut node in bar_query.iter_mut() {
+        node.width = Val::Percent(loading_assets.progress(&asset_server) * 100.);
+    }
+
+    let status = loading_assets
+        .iter()
+        .map(|tracked| format!("{:<16}{}", tracked.label, tracked.status(&asset_server)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut text in status_query.iter_mut() {
+        if text.0 != status {
+            text.0 = status.clone();
+        }
+    }
+}
+
+pub(crate) fn spawn_text_button(
+    parent: &mut ChildSpawnerCommands,
+    label: &str,
+    font: Handle<Font>,
+    font_size: f32,
+    marker: impl Bundle,
+) {
+    // just text, no box
+    parent
+        .spawn((
+            Button,
+            TabIndex(0),
+            marker,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text(label.to_string()),
+                TextFont {
+                    font,
+                    font_size,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+        });
+}
+
+pub fn spawn_load_error_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    load_failures: Res<LoadFailures>,
+) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(16.),
+                padding: UiRect::all(Val::Px(40.)),
+                ..default()
+            },
+            LoadErrorScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("limbo pass couldn't load".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
                     font_size: 48.,
                     ..default()
                 },
                 TextColor(Color::srgb(0.9, 0.9, 0.9)),
             ));
+
+            for failure in &load_failures.0 {
+                parent.spawn((
+                    Text(format!("{}\n{}", failure.path, failure.reason)),
+                    TextFont {
+                        font: font_handle.clone(),
+                        font_size: 16.,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                    Node {
+                        max_width: Val::Px(800.),
+                        ..default()
+                    },
+                ));
+            }
+
+            spawn_text_button(parent, "retry", font_handle.clone(), 32., LoadErrorButton::Retry);
+            // the theme is the only optional asset
+            if load_failures.only_optional() {
+                spawn_text_button(
+                    parent,
+                    "continue without music",
+                    font_handle.clone(),
+                    24.,
+                    LoadErrorButton::ContinueWithoutMusic,
+                );
+            }
         });
 }
 
+pub fn handle_load_error_buttons(
+    mut interaction_query: ButtonActionQuery<LoadErrorButton>,
+    mut audio_state: ResMut<ThemeState>,
+    mut state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, load_error_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match load_error_button {
+                // the loaders run again on entering `Loading`, which requests failed assets anew
+                LoadErrorButton::Retry => state.set(AppState::Loading),
+                LoadErrorButton::ContinueWithoutMusic => {
+                    audio_state.available = false;
+                    state.set(AppState::Menu);
+                }
+            }
+        }
+    }
+}
+
+pub fn cleanup_load_error_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<LoadErrorScreen>>,
+    mut load_failures: ResMut<LoadFailures>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    load_failures.0.clear();
+}
+
 pub fn spawn_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
     
@@ -140,99 +643,585 @@ pub fn spawn_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(24.),
                 ..default()
             },
             MenuScreen,
+            TabGroup::new(0),
         ))
         .with_children(|parent| {
-            // "head to limbo pass" button - just text, no box
-            parent
-                .spawn((
-                    Button,
-                ))
-                .with_children(|button| {
-                    button.spawn((
-                        Text("head to limbo pass".to_string()),
-                        TextFont {
-                            font: font_handle,
-                            font_size: 48.,
-                            ..default()
-                        },
-                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
-                    ));
-                });
+            spawn_text_button(parent, "head to limbo pass", font_handle.clone(), 48., MenuButton::Play);
+            spawn_text_button(parent, "settings", font_handle.clone(), 24., MenuButton::Settings);
         });
 }
 
-pub fn handle_play_button(
-    mut interaction_query: ButtonInteractionQuery,
-    mut text_query: Query<&mut TextColor>,
+pub fn handle_menu_buttons(
+    mut interaction_query: ButtonActionQuery<MenuButton>,
     mut state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, menu_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match menu_button {
+                MenuButton::Play => state.set(AppState::LevelSelect),
+                MenuButton::Settings => state.set(AppState::Settings),
+            }
+        }
+    }
+}
+
+fn binding_row_label(action: Action, bindings: &Bindings, rebinding: &Rebinding) -> String {
+    let keys = if rebinding.action == Some(action) && !rebinding.captured() {
+        "press a key...".to_string()
+    } else {
+        bindings.label(action)
+    };
+    format!("{:<14}{}", action.label(), keys)
+}
+
+pub fn spawn_controls_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            BackgroundColor(hex_to_color("0a0e17").with_alpha(0.8)),
+            GlobalZIndex(1),
+            ControlsScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("controls".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+            parent.spawn((
+                Text("pick an action then press one or more keys or pad buttons, enter / esc / start when done".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+
+            for action in Action::ALL {
+                parent
+                    .spawn((
+                        Button,
+                        TabIndex(0),
+                        ControlsButton::Rebind(action),
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            Text(binding_row_label(action, &bindings, &rebinding)),
+                            TextFont {
+                                font: font_handle.clone(),
+                                font_size: 24.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                            BindingText(action),
+                        ));
+                    });
+            }
+
+            parent.spawn(Node {
+                height: Val::Px(16.),
+                ..default()
+            });
+            spawn_text_button(parent, "reset", font_handle.clone(), 24., ControlsButton::Reset);
+            spawn_text_button(parent, "back", font_handle.clone(), 24., ControlsButton::Back);
+        });
+}
+
+pub fn handle_controls_buttons(
+    mut interaction_query: ButtonActionQuery<ControlsButton>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    pause_state: Option<Res<State<PauseState>>>,
+    mut state: ResMut<NextState<AppState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    for (interaction, controls_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match controls_button {
+                ControlsButton::Rebind(action) => {
+                    *rebinding = Rebinding {
+                        action: Some(*action),
+                        ..default()
+                    };
+                }
+                ControlsButton::Reset => {
+                    *bindings = Bindings::default();
+                    *rebinding = Rebinding::default();
+                }
+                // back to the settings screen it was opened from
+                ControlsButton::Back => {
+                    if pause_state.is_some() {
+                        next_pause_state.set(PauseState::Settings);
+                    } else {
+                        state.set(AppState::Settings);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// While an action is selected on the controls screen, the first key pressed replaces its keys
+/// and the first gamepad button its buttons, further ones are added alongside. Stick bindings
+/// can't be captured here and are kept.
+pub fn capture_binding(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    // skip the frame the action was picked, so the gamepad press that picked it isn't bound
+    if rebinding.is_changed() {
+        return;
+    }
+
+    let pressed_keys = keys.get_just_pressed().map(|key| match key {
+        KeyCode::Escape | KeyCode::Enter => None,
+        key => Some(Binding::Key(*key)),
+    });
+    let pressed_buttons = gamepads.iter().flat_map(|gamepad| gamepad.get_just_pressed()).map(|button| match button {
+        GamepadButton::Start => None,
+        button => Some(Binding::GamepadButton(*button)),
+    });
+
+    for pressed in pressed_keys.chain(pressed_buttons) {
+        let Some(binding) = pressed else {
+            *rebinding = Rebinding::default();
+            return;
+        };
+        let action_bindings = bindings.0.entry(action).or_default();
+        match binding {
+            Binding::Key(_) if !rebinding.captured_key => {
+                action_bindings.retain(|bound| !matches!(bound, Binding::Key(_)));
+                rebinding.captured_key = true;
+            }
+            Binding::GamepadButton(_) if !rebinding.captured_button => {
+                action_bindings.retain(|bound| !matches!(bound, Binding::GamepadButton(_)));
+                rebinding.captured_button = true;
+            }
+            _ => {}
+        }
+        if !action_bindings.contains(&binding) {
+            action_bindings.push(binding);
+        }
+    }
+}
+
+pub fn refresh_binding_texts(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    mut text_query: Query<(&mut Text, &BindingText)>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (mut text, binding_text) in text_query.iter_mut() {
+        text.0 = binding_row_label(binding_text.0, &bindings, &rebinding);
+    }
+}
+
+pub fn cleanup_controls_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<ControlsScreen>>,
+    bindings: Res<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    *rebinding = Rebinding::default();
+    bindings.save();
+}
+
+/// Highlights buttons that are hovered, pressed or focused with a gamepad. The music toggle
+/// is left alone since its text color shows whether music is on.
+pub fn highlight_buttons(
+    focus: Res<InputFocus>,
+    buttons: Query<(Entity, &Interaction, &Children), With<Button>>,
+    mut text_query: Query<&mut TextColor, Without<MusicToggleText>>,
 ) {
     let purple_color = hex_to_color("AB69E7"); // Dark purple from point lights
-    
-    for (interaction, children) in interaction_query.iter_mut() {
+
+    for (entity, interaction, children) in buttons.iter() {
+        let highlighted = *interaction != Interaction::None || focus.0 == Some(entity);
         // Get the first child (the text entity)
         if let Some(child) = children.first().copied()
             && let Ok(mut text_color) = text_query.get_mut(child)
         {
-            match *interaction {
-                Interaction::Pressed => {
-                    state.set(AppState::Running);
-                }
-                Interaction::Hovered => {
-                    *text_color = TextColor(purple_color); // Dark purple on hover
+            text_color.set_if_neq(TextColor(if highlighted {
+                purple_color
+            } else {
+                Color::srgb(0.9, 0.9, 0.9)
+            }));
+        }
+    }
+}
+
+/// Moves focus between the buttons on screen with the d-pad and presses the focused one with
+/// south (A / cross), so menus and the music toggle work without a mouse.
+pub fn gamepad_focus_navigation(
+    gamepads: Query<&Gamepad>,
+    nav: TabNavigation,
+    rebinding: Res<Rebinding>,
+    mut focus: ResMut<InputFocus>,
+    mut interactions: Query<&mut Interaction, With<Button>>,
+    mut pressed: Local<Option<Entity>>,
+) {
+    // a gamepad press only lasts one frame, there's no mouse release to reset it
+    if let Some(entity) = pressed.take()
+        && let Ok(mut interaction) = interactions.get_mut(entity)
+        && *interaction == Interaction::Pressed
+    {
+        *interaction = Interaction::None;
+    }
+
+    // the focused button went away with its screen
+    if let Some(entity) = focus.0
+        && !interactions.contains(entity)
+    {
+        focus.clear();
+    }
+
+    for gamepad in gamepads.iter() {
+        let nav_action = if gamepad.just_pressed(GamepadButton::DPadDown) {
+            Some(NavAction::Next)
+        } else if gamepad.just_pressed(GamepadButton::DPadUp) {
+            Some(NavAction::Previous)
+        } else {
+            None
+        };
+        if let Some(nav_action) = nav_action
+            && let Ok(next) = nav.navigate(&focus, nav_action)
+        {
+            focus.set(next);
+        }
+
+        if gamepad.just_pressed(GamepadButton::South)
+            && rebinding.action.is_none()
+            && let Some(entity) = focus.0
+            && let Ok(mut interaction) = interactions.get_mut(entity)
+        {
+            *interaction = Interaction::Pressed;
+            *pressed = Some(entity);
+        }
+    }
+}
+
+pub fn cleanup_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn spawn_settings_screen(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            BackgroundColor(hex_to_color("0a0e17").with_alpha(0.8)),
+            GlobalZIndex(1),
+            SettingsScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("settings".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+
+            for kind in SettingKind::ALL {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(12.),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text(kind.label().to_string()),
+                            TextFont {
+                                font: font_handle.clone(),
+                                font_size: 24.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                            Node {
+                                width: Val::Px(280.),
+                                ..default()
+                            },
+                        ));
+                        spawn_text_button(row, "<", font_handle.clone(), 24., SettingsButton::Decrease(kind));
+                        row.spawn((
+                            Text(kind.value(&settings)),
+                            TextFont {
+                                font: font_handle.clone(),
+                                font_size: 24.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                            TextLayout::new_with_justify(Justify::Center),
+                            Node {
+                                width: Val::Px(120.),
+                                ..default()
+                            },
+                            SettingText(kind),
+                        ));
+                        spawn_text_button(row, ">", font_handle.clone(), 24., SettingsButton::Increase(kind));
+                    });
+            }
+
+            parent.spawn(Node {
+                height: Val::Px(16.),
+                ..default()
+            });
+            spawn_text_button(parent, "controls", font_handle.clone(), 24., SettingsButton::Controls);
+            spawn_text_button(parent, "back", font_handle.clone(), 24., SettingsButton::Back);
+        });
+}
+
+pub fn handle_settings_buttons(
+    mut interaction_query: ButtonActionQuery<SettingsButton>,
+    mut settings: ResMut<Settings>,
+    pause_state: Option<Res<State<PauseState>>>,
+    mut state: ResMut<NextState<AppState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    for (interaction, settings_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match settings_button {
+                SettingsButton::Decrease(kind) => kind.adjust(&mut settings, -1.0),
+                SettingsButton::Increase(kind) => kind.adjust(&mut settings, 1.0),
+                SettingsButton::Controls => {
+                    if pause_state.is_some() {
+                        next_pause_state.set(PauseState::Controls);
+                    } else {
+                        state.set(AppState::Controls);
+                    }
                 }
-                Interaction::None => {
-                    *text_color = TextColor(Color::srgb(0.9, 0.9, 0.9)); // Normal color
+                // back to wherever the screen was opened from
+                SettingsButton::Back => {
+                    if pause_state.is_some() {
+                        next_pause_state.set(PauseState::Paused);
+                    } else {
+                        state.set(AppState::Menu);
+                    }
                 }
             }
         }
     }
 }
 
-pub fn cleanup_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+pub fn refresh_setting_texts(settings: Res<Settings>, mut text_query: Query<(&mut Text, &SettingText)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, setting_text) in text_query.iter_mut() {
+        text.0 = setting_text.0.value(&settings);
+    }
+}
+
+pub fn cleanup_settings_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<SettingsScreen>>,
+    settings: Res<Settings>,
+) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
+    settings.save();
 }
 
-pub fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuScreen>>) {
+pub fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let pressed = keys.just_pressed(KeyCode::Escape)
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if !pressed {
+        return;
+    }
+    match pause_state.get() {
+        PauseState::Playing => next_pause_state.set(PauseState::Paused),
+        PauseState::Paused => next_pause_state.set(PauseState::Playing),
+        PauseState::Settings | PauseState::Controls | PauseState::Failed | PauseState::Photo => {}
+    }
+}
+
+pub fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(16.),
+                ..default()
+            },
+            BackgroundColor(hex_to_color("0a0e17").with_alpha(0.8)),
+            GlobalZIndex(1),
+            PauseScreen,
+            TabGroup::new(0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("paused".to_string()),
+                TextFont {
+                    font: font_handle.clone(),
+                    font_size: 48.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+            spawn_text_button(parent, "resume", font_handle.clone(), 32., PauseButton::Resume);
+            spawn_text_button(parent, "settings", font_handle.clone(), 32., PauseButton::Settings);
+            spawn_text_button(parent, "restart", font_handle.clone(), 32., PauseButton::Restart);
+            spawn_text_button(parent, "change level", font_handle.clone(), 32., PauseButton::ChangeLevel);
+            spawn_text_button(parent, "quit to menu", font_handle.clone(), 32., PauseButton::Quit);
+        });
+}
+
+pub fn handle_pause_buttons(
+    mut interaction_query: ButtonActionQuery<PauseButton>,
+    mut state: ResMut<NextState<AppState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    for (interaction, pause_button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            match pause_button {
+                PauseButton::Resume => next_pause_state.set(PauseState::Playing),
+                PauseButton::Settings => next_pause_state.set(PauseState::Settings),
+                PauseButton::Restart => state.set(AppState::Restarting),
+                PauseButton::ChangeLevel => state.set(AppState::LevelSelect),
+                PauseButton::Quit => state.set(AppState::Menu),
+            }
+        }
+    }
+}
+
+pub fn cleanup_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseScreen>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
 }
 
-pub fn spawn_controls_text(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let controls_text = "wander
---------------------
-space bar
-← ↑ ↓ →
-w a s d
+pub fn cleanup_game(mut commands: Commands, query: Query<Entity, With<InGame>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn restart(mut state: ResMut<NextState<AppState>>) {
+    state.set(AppState::Running);
+}
 
+pub fn spawn_controls_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    audio_state: Res<ThemeState>,
+) {
+    let mut controls_text = "wander
+--------------------
+".to_string();
+    for action in Action::ALL {
+        controls_text += &format!("{:<13}{}\n", action.label(), bindings.key_label(action));
+    }
+    controls_text += "
 look
 --------------------
 hold ctrl + move mouse (orbit)
 scroll (zoom)
-right click (pan)";
+right click (pan)
+right stick (orbit)";
 
     // Load a font that supports Unicode arrows from assets
     let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
 
     commands
-        .spawn(Node {
-            width: Val::Px(200.),
-            height: Val::Px(10.),
-            position_type: PositionType::Absolute,
-            justify_content: JustifyContent::FlexStart,
-            align_items: AlignItems::FlexStart,
-            left: Val::Px(10.),
-            top: Val::Px(10.),
-            ..default()
-        })
+        .spawn((
+            Node {
+                width: Val::Px(200.),
+                height: Val::Px(10.),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::FlexStart,
+                align_items: AlignItems::FlexStart,
+                left: Val::Px(10.),
+                top: Val::Px(10.),
+                ..default()
+            },
+            InGame,
+        ))
         .with_children(|parent| {
             parent.spawn((
-                Text(controls_text.to_string()),
+                Text(controls_text),
                 TextFont {
                     font: font_handle.clone(),
                     font_size: 16.,
@@ -242,6 +1231,11 @@ right click (pan)";
             ));
         });
 
+    // No music to toggle when the player continued without the theme
+    if !audio_state.available {
+        return;
+    }
+
     // Spawn music toggle button in top right
     commands
         .spawn((
@@ -256,11 +1250,14 @@ right click (pan)";
                 ..default()
             },
             MusicToggleButton,
+            TabGroup::new(1),
+            InGame,
         ))
         .with_children(|parent| {
             parent
                 .spawn((
                     Button,
+                    TabIndex(0),
                     BackgroundColor(Color::NONE),
                 ))
                 .with_children(|button| {
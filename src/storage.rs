@@ -0,0 +1,75 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+// Tiny key/value persistence for things that should survive a restart (bindings, settings, ...).
+// Values are stored as RON: one file per key on native, one `localStorage` entry per key on the web.
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let contents = read(key)?;
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            bevy::log::warn!("Ignoring saved {}: {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => write(key, &contents),
+        Err(e) => bevy::log::warn!("Failed to serialize {}: {}", key, e),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> std::path::PathBuf {
+    use std::path::PathBuf;
+
+    let base = std::env::var_os("LIMBO_PASS_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("XDG_DATA_HOME").map(|dir| PathBuf::from(dir).join("limbo_pass")))
+        .or_else(|| std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("limbo_pass")))
+        .or_else(|| std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".local/share/limbo_pass")))
+        .unwrap_or_else(|| PathBuf::from("saves"));
+    base.join(format!("{key}.ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, contents: &str) {
+    let path = path(key);
+    if let Some(parent) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        bevy::log::warn!("Failed to create {}: {}", parent.display(), e);
+        return;
+    }
+    if let Err(e) = std::fs::write(&path, contents) {
+        bevy::log::warn!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("limbo_pass.{key}")).ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, contents: &str) {
+    let Some(storage) = local_storage() else {
+        bevy::log::warn!("localStorage unavailable, {} not saved", key);
+        return;
+    };
+    if storage.set_item(&format!("limbo_pass.{key}"), contents).is_err() {
+        bevy::log::warn!("Failed to save {} to localStorage", key);
+    }
+}
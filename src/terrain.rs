@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::gltf_collider::{ColliderShape, MeshGeometry};
+
+// Colliders for the `TERRAIN` mesh. A trimesh follows the mesh exactly, a heightfield samples
+// it on a regular grid, which is cheaper to collide with and doesn't let the Form's ball slip
+// through the cracks between triangles. Only meshes that are a height function (one surface
+// above every point, no overhangs or holes) can be sampled, the rest fall back to a trimesh.
+
+/// Surfaces this far apart above the same point count as an overhang.
+const HEIGHT_TOLERANCE: f32 = 0.05;
+/// Points this close outside a triangle still count as on it, so samples on shared edges hit.
+const EDGE_TOLERANCE: f32 = 1e-4;
+
+/// How the terrain collides. Insert before adding `ScenesPlugin` to change it.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerrainCollider {
+    /// Every triangle of the mesh, as modeled.
+    #[default]
+    Trimesh,
+    /// Heights sampled on a `resolution` x `resolution` grid over the mesh's footprint.
+    Heightfield { resolution: usize },
+}
+
+/// Builds the collider for the terrain, falling back to a trimesh when it can't be a heightfield.
+pub fn terrain_collider(geometry: &MeshGeometry, shape: TerrainCollider) -> Option<Collider> {
+    if let TerrainCollider::Heightfield { resolution } = shape {
+        let resolution = resolution.max(1);
+        match heightfield(&geometry.vertices, &geometry.triangles, resolution) {
+            Ok(collider) => {
+                bevy::log::info!("Terrain heightfield collider sampled at {}x{}", resolution, resolution);
+                return Some(collider);
+            }
+            Err(reason) => bevy::log::warn!("Terrain can't be a heightfield ({}), using a trimesh", reason),
+        }
+    }
+
+    let collider = geometry.collider(ColliderShape::Trimesh)?;
+    bevy::log::info!("Terrain trimesh collider with {} triangles", geometry.triangles.len());
+    Some(collider)
+}
+
+/// Samples the highest surface above each point of the grid.
+fn heightfield(vertices: &[Vec3], triangles: &[[u32; 3]], resolution: usize) -> Result<Collider, String> {
+    let (min, max) = vertices
+        .iter()
+        .fold((Vec3::MAX, Vec3::MIN), |(min, max), &vertex| (min.min(vertex), max.max(vertex)));
+    let size = Vec2::new(max.x - min.x, max.z - min.z);
+    if triangles.is_empty() || !(size.x > 0.0 && size.y > 0.0) {
+        return Err("the mesh has no footprint".to_string());
+    }
+    let cell_size = size / resolution as f32;
+    let cell_of = |x: f32, z: f32| {
+        let cell = ((Vec2::new(x, z) - Vec2::new(min.x, min.z)) / cell_size).floor();
+        let clamp = |value: f32| (value.max(0.0) as usize).min(resolution - 1);
+        (clamp(cell.x), clamp(cell.y))
+    };
+
+    // which triangles reach into each grid cell, so each sample only tests a few
+    let mut cells = vec![Vec::new(); resolution * resolution];
+    for (index, triangle) in triangles.iter().enumerate() {
+        let corners = triangle.map(|vertex| vertices[vertex as usize]);
+        let low = corners[0].min(corners[1]).min(corners[2]);
+        let high = corners[0].max(corners[1]).max(corners[2]);
+        let (first_x, first_z) = cell_of(low.x, low.z);
+        let (last_x, last_z) = cell_of(high.x, high.z);
+        for z in first_z..=last_z {
+            for x in first_x..=last_x {
+                cells[z * resolution + x].push(index);
+            }
+        }
+    }
+
+    // rows run along z and columns along x, stored column by column
+    let samples = resolution + 1;
+    let mut heights = vec![0.0; samples * samples];
+    for column in 0..samples {
+        for row in 0..samples {
+            let x = min.x + size.x * column as f32 / resolution as f32;
+            let z = min.z + size.y * row as f32 / resolution as f32;
+            let (cell_x, cell_z) = cell_of(x, z);
+
+            let mut surfaces = cells[cell_z * resolution + cell_x]
+                .iter()
+                .filter_map(|&index| height_in_triangle(triangles[index].map(|vertex| vertices[vertex as usize]), x, z));
+            let Some(first) = surfaces.next() else {
+                return Err(format!("there's a hole at ({x:.1}, {z:.1})"));
+            };
+            let (low, high) = surfaces.fold((first, first), |(low, high), height| (low.min(height), high.max(height)));
+            if high - low > HEIGHT_TOLERANCE {
+                return Err(format!("there's an overhang at ({x:.1}, {z:.1})"));
+            }
+            heights[column * samples + row] = high;
+        }
+    }
+
+    let heightfield = Collider::heightfield(heights, samples, samples, Vec3::new(size.x, 1.0, size.y));
+    // the heightfield is centered on its origin, the mesh's footprint might not be
+    let center = Vec3::new(min.x + size.x / 2.0, 0.0, min.z + size.y / 2.0);
+    Ok(Collider::compound(vec![(center, Quat::IDENTITY, heightfield)]))
+}
+
+/// The height of the triangle above (`x`, `z`), if that point is inside it seen from above.
+fn height_in_triangle([a, b, c]: [Vec3; 3], x: f32, z: f32) -> Option<f32> {
+    let (ab, ac) = (Vec2::new(b.x - a.x, b.z - a.z), Vec2::new(c.x - a.x, c.z - a.z));
+    let ap = Vec2::new(x - a.x, z - a.z);
+    let area = ab.perp_dot(ac);
+    // walls have no area seen from above, the triangles around them carry the height
+    if area.abs() < f32::EPSILON {
+        return None;
+    }
+    let v = ap.perp_dot(ac) / area;
+    let w = ab.perp_dot(ap) / area;
+    let u = 1.0 - v - w;
+    (u >= -EDGE_TOLERANCE && v >= -EDGE_TOLERANCE && w >= -EDGE_TOLERANCE).then(|| a.y * u + b.y * v + c.y * w)
+}
@@ -1,29 +1,77 @@
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioSource};
+use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioSource, AudioTween};
+
+use crate::levels::CurrentLevel;
+use crate::setup::{AppState, PauseState};
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Loading), load)
+            .add_systems(OnEnter(AppState::Running), play)
+            .add_systems(OnExit(PauseState::Playing), pause)
+            .add_systems(OnEnter(PauseState::Playing), resume)
+            .add_systems(OnExit(AppState::Running), stop);
+    }
+}
 
 #[derive(Resource)]
 pub struct ThemeState {
+    /// The current level's track, each level brings its own, see `levels`.
     pub loop_handle: Handle<AudioSource>,
     pub instance: Option<Handle<AudioInstance>>,
     pub is_playing: bool,
+    /// False when the theme failed to load and the player chose to continue without it.
+    pub available: bool,
 }
 
-pub fn load(mut commands: Commands, asset_server: ResMut<AssetServer>) {
-    let loop_handle = asset_server.load("audio/overworld.ogg");
+pub fn load(mut commands: Commands) {
     let theme_state = ThemeState {
-        loop_handle,
+        loop_handle: Handle::default(),
         instance: None,
         is_playing: false,
+        available: true,
     };
 
     commands.insert_resource(theme_state);
 }
 
-pub fn play(mut audio_state: ResMut<ThemeState>, audio: Res<Audio>) {
-    if audio_state.instance.is_none() {
+pub fn play(mut audio_state: ResMut<ThemeState>, audio: Res<Audio>, current_level: Res<CurrentLevel>) {
+    audio_state.loop_handle = current_level.music.clone();
+    if audio_state.available && audio_state.instance.is_none() {
         let instance = audio.play(audio_state.loop_handle.clone()).looped().handle();
         audio_state.instance = Some(instance);
         audio_state.is_playing = true;
         bevy::log::info!("Music started");
     }
 }
+
+/// Holds the theme while the game is paused, without touching the music toggle.
+pub fn pause(audio_state: Res<ThemeState>, mut audio_instances: ResMut<Assets<AudioInstance>>) {
+    if audio_state.is_playing
+        && let Some(instance_handle) = &audio_state.instance
+        && let Some(instance) = audio_instances.get_mut(instance_handle)
+    {
+        instance.pause(AudioTween::default());
+    }
+}
+
+pub fn resume(audio_state: Res<ThemeState>, mut audio_instances: ResMut<Assets<AudioInstance>>) {
+    if audio_state.is_playing
+        && let Some(instance_handle) = &audio_state.instance
+        && let Some(instance) = audio_instances.get_mut(instance_handle)
+    {
+        instance.resume(AudioTween::default());
+    }
+}
+
+pub fn stop(mut audio_state: ResMut<ThemeState>, mut audio_instances: ResMut<Assets<AudioInstance>>) {
+    if let Some(instance_handle) = audio_state.instance.take()
+        && let Some(instance) = audio_instances.get_mut(&instance_handle)
+    {
+        instance.stop(AudioTween::default());
+    }
+    audio_state.is_playing = false;
+}
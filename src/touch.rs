@@ -0,0 +1,281 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraController, LookTransform};
+
+use crate::input::{self, Action, ActionState};
+use crate::settings::Settings;
+use crate::setup::{AppState, PauseState};
+
+// On-screen controls for phones and tablets. A thumb on the left of the screen is a floating
+// joystick, its ring jumps to wherever the thumb lands and rests in the corner otherwise. The
+// round button on the bottom right lifts, and two fingers anywhere else orbit and pinch-zoom the
+// camera. Everything feeds the same `ActionState` the keyboard and gamepad do.
+
+const JOYSTICK_RADIUS: f32 = 80.0;
+const KNOB_SIZE: f32 = 64.0;
+const LIFT_BUTTON_SIZE: f32 = 120.0;
+const MARGIN: f32 = 40.0;
+/// Space between the screen's edge, or the joystick, and HUD in the bottom left.
+const HUD_MARGIN: f32 = 10.0;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TouchControls>()
+            .add_systems(PreUpdate, (
+                touch_actions.after(input::update_actions).run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(Update, (
+                spawn_touch_controls.run_if(in_state(AppState::Running)),
+                update_touch_controls.run_if(in_state(AppState::Running)),
+                keep_clear_of_joystick.run_if(in_state(AppState::Running)),
+                touch_camera.run_if(in_state(PauseState::Playing)),
+            ))
+            .add_systems(OnExit(AppState::Running), (
+                reset_touch_controls,
+            ));
+    }
+}
+
+/// Which fingers are currently driving the joystick and lift button.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    /// Set by the first touch, the on-screen controls stay hidden on devices that never touch.
+    pub enabled: bool,
+    joystick: Option<(u64, Vec2)>,
+    lift: Option<u64>,
+    knob: Vec2,
+}
+
+impl TouchControls {
+    fn owns(&self, id: u64) -> bool {
+        self.joystick.is_some_and(|(joystick, _)| joystick == id) || self.lift == Some(id)
+    }
+}
+
+#[derive(Component)]
+pub struct TouchControlsUi;
+
+#[derive(Component)]
+pub struct JoystickRing;
+
+/// HUD in the bottom left, moved up out of the joystick's way once the touch controls show.
+#[derive(Component)]
+pub struct ClearOfJoystick;
+
+#[derive(Component)]
+pub struct JoystickKnob;
+
+#[derive(Component)]
+pub struct LiftButton;
+
+pub fn touch_actions(
+    touches: Res<Touches>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut controls: ResMut<TouchControls>,
+    mut actions: ResMut<ActionState>,
+) {
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    let size = window.size();
+    let lift_center = size - Vec2::splat(MARGIN + LIFT_BUTTON_SIZE / 2.0);
+
+    for touch in touches.iter_just_pressed() {
+        controls.enabled = true;
+        let position = touch.position();
+        if controls.joystick.is_none() && position.x < size.x * 0.4 && position.y > size.y * 0.4 {
+            controls.joystick = Some((touch.id(), position));
+        } else if controls.lift.is_none() && position.distance(lift_center) < LIFT_BUTTON_SIZE / 2.0 {
+            controls.lift = Some(touch.id());
+        }
+    }
+
+    for touch in touches.iter_just_released().chain(touches.iter_just_canceled()) {
+        if controls.joystick.is_some_and(|(id, _)| id == touch.id()) {
+            controls.joystick = None;
+        }
+        if controls.lift == Some(touch.id()) {
+            controls.lift = None;
+        }
+    }
+
+    controls.knob = Vec2::ZERO;
+    if let Some((id, start)) = controls.joystick
+        && let Some(touch) = touches.get_pressed(id)
+    {
+        let knob = (touch.position() - start).clamp_length_max(JOYSTICK_RADIUS);
+        controls.knob = knob;
+        // screen y grows downwards, pushing the thumb up means forward
+        let stick = knob / JOYSTICK_RADIUS;
+        actions.press(Action::Forward, -stick.y);
+        actions.press(Action::Backward, stick.y);
+        actions.press(Action::TurnLeft, -stick.x);
+        actions.press(Action::TurnRight, stick.x);
+    }
+
+    if controls.lift.is_some() {
+        actions.press(Action::Lift, 1.0);
+    }
+}
+
+pub fn spawn_touch_controls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    controls: Res<TouchControls>,
+    existing: Query<(), With<TouchControlsUi>>,
+) {
+    if !controls.enabled || !existing.is_empty() {
+        return;
+    }
+    let font_handle = asset_server.load("font/NotoSansMono-Bold.ttf");
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Px(JOYSTICK_RADIUS * 2.),
+                height: Val::Px(JOYSTICK_RADIUS * 2.),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                left: Val::Px(MARGIN),
+                bottom: Val::Px(MARGIN),
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(Color::srgba(0.9, 0.9, 0.9, 0.1)),
+            TouchControlsUi,
+            JoystickRing,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(KNOB_SIZE),
+                    height: Val::Px(KNOB_SIZE),
+                    ..default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(Color::srgba(0.9, 0.9, 0.9, 0.3)),
+                JoystickKnob,
+            ));
+        });
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Px(LIFT_BUTTON_SIZE),
+                height: Val::Px(LIFT_BUTTON_SIZE),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                right: Val::Px(MARGIN),
+                bottom: Val::Px(MARGIN),
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(Color::srgba(0.9, 0.9, 0.9, 0.1)),
+            TouchControlsUi,
+            LiftButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("lift".to_string()),
+                TextFont {
+                    font: font_handle,
+                    font_size: 24.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+        });
+}
+
+pub fn update_touch_controls(
+    controls: Res<TouchControls>,
+    mut ring_query: Query<&mut Node, With<JoystickRing>>,
+    mut knob_query: Query<&mut UiTransform, With<JoystickKnob>>,
+    mut lift_query: Query<&mut BackgroundColor, With<LiftButton>>,
+) {
+    if !controls.is_changed() {
+        return;
+    }
+    for mut node in ring_query.iter_mut() {
+        // centered on where the thumb landed, back in the corner once it lifts
+        (node.left, node.top, node.bottom) = match controls.joystick {
+            Some((_, start)) => (
+                Val::Px(start.x - JOYSTICK_RADIUS),
+                Val::Px(start.y - JOYSTICK_RADIUS),
+                Val::Auto,
+            ),
+            None => (Val::Px(MARGIN), Val::Auto, Val::Px(MARGIN)),
+        };
+    }
+    for mut transform in knob_query.iter_mut() {
+        transform.translation = Val2::px(controls.knob.x, controls.knob.y);
+    }
+    for mut background in lift_query.iter_mut() {
+        let alpha = if controls.lift.is_some() { 0.3 } else { 0.1 };
+        *background = BackgroundColor(Color::srgba(0.9, 0.9, 0.9, alpha));
+    }
+}
+
+pub fn keep_clear_of_joystick(controls: Res<TouchControls>, mut nodes: Query<&mut Node, With<ClearOfJoystick>>) {
+    let bottom = if controls.enabled {
+        Val::Px(MARGIN + JOYSTICK_RADIUS * 2.0 + HUD_MARGIN)
+    } else {
+        Val::Px(HUD_MARGIN)
+    };
+    for mut node in nodes.iter_mut() {
+        if node.bottom != bottom {
+            node.bottom = bottom;
+        }
+    }
+}
+
+/// Two fingers that aren't on the joystick or lift button orbit the camera by dragging and
+/// zoom it by pinching.
+pub fn touch_camera(
+    touches: Res<Touches>,
+    controls: Res<TouchControls>,
+    settings: Res<Settings>,
+    mut cameras: Query<(&OrbitCameraController, &mut LookTransform)>,
+    mut last_pinch: Local<Option<(Vec2, f32)>>,
+) {
+    let fingers: Vec<Vec2> = touches
+        .iter()
+        .filter(|touch| !controls.owns(touch.id()))
+        .map(|touch| touch.position())
+        .collect();
+    let [a, b] = fingers[..] else {
+        *last_pinch = None;
+        return;
+    };
+
+    let middle = (a + b) / 2.0;
+    let spread = a.distance(b).max(1.0);
+    if let Some((last_middle, last_spread)) = *last_pinch {
+        for (controller, mut look) in cameras.iter_mut() {
+            if !controller.enabled {
+                continue;
+            }
+            input::orbit_look(&mut look, (middle - last_middle) * 0.005 * settings.orbit_scale());
+            let offset = (look.eye - look.target) * (last_spread / spread);
+            look.eye = look.target + offset.clamp_length(5.0, 300.0);
+        }
+    }
+    *last_pinch = Some((middle, spread));
+}
+
+pub fn reset_touch_controls(
+    mut commands: Commands,
+    mut controls: ResMut<TouchControls>,
+    query: Query<Entity, With<TouchControlsUi>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    controls.joystick = None;
+    controls.lift = None;
+    controls.knob = Vec2::ZERO;
+}